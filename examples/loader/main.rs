@@ -21,56 +21,42 @@ object.display_inner();
 }
 
 use rhai_dylib::loader::{libloading::Libloading, Loader};
-use rhai_dylib::rhai::{config::hashing::set_hashing_seed, Engine, ImmutableString};
+use rhai_dylib::rhai::{config::hashing::set_hashing_seed, Engine};
 
 pub fn build_engine() -> Engine {
     if let Err(value) = set_hashing_seed(Some([1, 2, 3, 4])) {
         panic!("ahash seed has been overridden by a plugin: {value:?}");
     }
 
-    // Inspect the type id generated by the binary.
-    println!("engine: {:?}", std::any::TypeId::of::<ImmutableString>());
-
     let mut loader = Libloading::new();
     let mut engine = rhai_dylib::rhai::Engine::new();
 
-    // Load the plugin.
     #[cfg(target_os = "linux")]
-    engine.register_global_module(
-        loader
-            .load(std::path::PathBuf::from_iter([
-                env!("CARGO_MANIFEST_DIR"),
-                "target",
-                "debug",
-                "examples",
-                "libdynamic_library.so",
-            ]))
-            .expect("failed to load plugin"),
-    );
+    let library = "libdynamic_library.so";
     #[cfg(target_os = "macos")]
-    engine.register_global_module(
-        loader
-            .load(std::path::PathBuf::from_iter([
-                env!("CARGO_MANIFEST_DIR"),
-                "target",
-                "debug",
-                "examples",
-                "libdynamic_library.dylib",
-            ]))
-            .expect("failed to load plugin"),
-    );
+    let library = "libdynamic_library.dylib";
     #[cfg(target_os = "windows")]
-    engine.register_global_module(
-        loader
-            .load(std::path::PathBuf::from_iter([
-                env!("CARGO_MANIFEST_DIR"),
-                "target",
-                "debug",
-                "examples",
-                "libdynamic_library.dll",
-            ]))
-            .expect("failed to load plugin"),
+    let library = "libdynamic_library.dll";
+
+    // Load the plugin.
+    let plugin = loader
+        .load(std::path::PathBuf::from_iter([
+            env!("CARGO_MANIFEST_DIR"),
+            "target",
+            "debug",
+            "examples",
+            library,
+        ]))
+        .expect("failed to load plugin");
+
+    println!(
+        "loaded {} v{} by {}",
+        plugin.metadata.name,
+        plugin.metadata.version,
+        plugin.metadata.authors.join(", ")
     );
 
+    engine.register_global_module(plugin.module);
+
     engine
 }
@@ -1,4 +1,4 @@
-use rhai_dylib::rhai::{config::hashing::set_hashing_seed, ImmutableString};
+use rhai_dylib::rhai::config::hashing::set_hashing_seed;
 use rhai_dylib::{module_resolvers::libloading::DylibModuleResolver, rhai};
 
 fn main() {
@@ -6,8 +6,6 @@ fn main() {
         panic!("ahash seed has been overridden by a plugin: {value:?}");
     }
 
-    println!("main: {:?}", std::any::TypeId::of::<ImmutableString>());
-
     let mut engine = rhai::Engine::new();
 
     engine.set_module_resolver(DylibModuleResolver::new());
@@ -3,6 +3,8 @@
 #![warn(clippy::all)]
 #![warn(clippy::pedantic)]
 #![warn(clippy::cargo)]
+// Features mirror the names of rhai's features.
+#![allow(clippy::negative_feature_names)]
 
 #[cfg(not(target_os = "linux"))]
 #[cfg(not(target_os = "macos"))]
@@ -13,6 +15,8 @@ compile_error!("unsupported platform - only Linux, macOS and Windows are support
 pub mod loader;
 /// A Rhai module resolver loading dynamic libraries.
 pub mod module_resolvers;
+/// Items exported by plugins to describe themselves to a host.
+pub mod plugin;
 
 /// Re-exporting rhai to prevent version mismatch.
 pub use rhai;
@@ -6,12 +6,40 @@
 //! The name of the function must be the same as [`MODULE_ENTRYPOINT`].
 //!
 //! ```rust,ignore
-//! fn module_entrypoint() -> rhai::Shared<rhai::Module> {
+//! extern "C" fn module_entrypoint() -> rhai::Shared<rhai::Module> {
 //!     // ...
 //! }
 //! ```
 //!
-//! You can easily, for example, implement and export your module using Rhai's [plugin modules](https://rhai.rs/book/plugins/module.html).
+//! Libraries can also export a [`MODULE_ENGINE_ENTRYPOINT`], following the [`EngineEntrypoint`] prototype,
+//! to register types, operators or custom syntax in the [`rhai::Engine`] of the host, see [`Libloading::load_with_engine`].
+//!
+//! Entrypoints following the [`EntrypointV2`] prototype, named [`MODULE_ENTRYPOINT_V2`], also receive
+//! the context of the host, see [`HostContext`], and return an error instead of panicking, see
+//! [`crate::plugin::catch_panic`]. They are preferred when a library exports both.
+//!
+//! ```rust,ignore
+//! extern "C" fn module_entrypoint_v2(context: &HostContext) -> Result<rhai::Shared<rhai::Module>, String> {
+//!     rhai_dylib::plugin::catch_panic(|| {
+//!         // ...
+//!     })
+//! }
+//! ```
+//!
+//! Entrypoints can be looked up under other names, to tell apart plugins of several frameworks
+//! in the same process or to rename an entrypoint while still loading old plugins, see [`Libloading::set_entrypoint_symbols`].
+//!
+//! Libraries written against the C interface of [`crate::plugin::ffi`] export a [`MODULE_FFI_ENTRYPOINT`] instead,
+//! following the [`FfiEntrypoint`] prototype. They do not need to be built with the same compiler as the host.
+//!
+//! Before calling the entrypoint, the loader checks that the library was built in the same environment
+//! as the host and shares its type ids, then hands it the hashing seed of the host. After calling it,
+//! the loader registers the functions of the module again so that they are hashed like the functions
+//! of the host, and checks that the library hashes functions like the host.
+//! The library must export the functions described in [`crate::plugin`].
+//!
+//! The [`crate::declare_plugin`] macro generates all of those functions. You can easily, for example,
+//! implement and export your module using Rhai's [plugin modules](https://rhai.rs/book/plugins/module.html).
 //!
 //! ```rust,ignore
 //! use rhai::plugin::*;
@@ -27,21 +55,33 @@
 //!     }
 //! }
 //!
-//! // The entrypoint function of your module.
-//! // `extern "C"` can be omitted if you are using the `rust` feature.
-//! #[no_mangle]
-//! extern "C" fn module_entrypoint() -> rhai::Shared<rhai::Module> {
-//!     // Build your module.
-//!     rhai::exported_module!(my_api).into()
-//! }
+//! // Generate the entrypoint of your module and the functions checked by the loader.
+//! rhai_dylib::declare_plugin!(my_api);
 //! ```
 
-use super::Loader;
+#[cfg(feature = "pinning")]
+use super::pinning::{DigestPins, PinError};
+#[cfg(feature = "signature")]
+use super::signature::{SignatureError, VerifyingKey};
+use super::{LoadWarning, LoadedPlugin, Loader, PluginHandle, PluginMetadata};
+use crate::plugin::compatibility::{Compatibility, IncompatiblePlugin};
+use crate::plugin::context::{HostContext, Services};
+use crate::plugin::functions::map_functions;
+use crate::plugin::hashing::{Hashing, HashingMismatch};
+use crate::plugin::type_ids::{TypeIdMismatch, TypeIds};
+use crate::plugin::{
+    MetadataFn, ModuleNamesFn, NamedEntrypoint, SetHashingSeedFn, MODULE_COMPATIBILITY,
+    MODULE_HASHING, MODULE_METADATA, MODULE_NAMED_ENTRYPOINT, MODULE_NAMES,
+    MODULE_SET_HASHING_SEED, MODULE_TYPE_IDS,
+};
+use rhai::plugin::{PluginFunc, RhaiFunc};
+use rhai::NativeCallContext;
 
-/// Entrypoint prototype for a Rhai module "constructor".
-pub type Entrypoint = fn() -> rhai::Shared<rhai::Module>;
-/// The name of the function that will be called to update the [`rhai::Engine`].
-pub const MODULE_ENTRYPOINT: &str = "module_entrypoint";
+pub use crate::plugin::ffi::FfiEntrypoint;
+pub use crate::plugin::{
+    EngineEntrypoint, Entrypoint, EntrypointV2, MODULE_ENGINE_ENTRYPOINT, MODULE_ENTRYPOINT,
+    MODULE_ENTRYPOINT_V2, MODULE_FFI_ENTRYPOINT,
+};
 
 /// Loading dynamic libraries using the [`libloading`](https://github.com/nagisa/rust_libloading) crate.
 ///
@@ -63,15 +103,564 @@ pub const MODULE_ENTRYPOINT: &str = "module_entrypoint";
 ///     print_second();
 /// ");
 /// ```
+#[allow(clippy::struct_excessive_bools)]
 pub struct Libloading {
-    /// Libraries loaded in memory.
-    libraries: Vec<libloading::Library>,
+    /// Libraries loaded in memory, also kept alive by the modules they produced.
+    libraries: std::collections::BTreeMap<PluginHandle, OpenLibrary>,
+    /// Handle given to the next loaded library.
+    next_handle: u64,
+    /// Is the build environment of libraries checked before calling their entrypoint.
+    compatibility_check: bool,
+    /// Is the hashing seed of the host handed to libraries before calling their entrypoint.
+    hashing_seed_sharing: bool,
+    /// What to do when a library hashes functions differently from the host.
+    hashing_policy: HashingPolicy,
+    /// What to do when the type ids of a library differ from the host's.
+    type_id_policy: TypeIdPolicy,
+    /// Are libraries loaded from a copy in [`Self::shadow_copy_directory`].
+    shadow_copy: bool,
+    /// Directory of the copies of libraries, named by content hash.
+    shadow_copy_directory: std::path::PathBuf,
+    /// Name of the host, handed to libraries in their [`HostContext`].
+    host_name: String,
+    /// Version of the host, handed to libraries in their [`HostContext`].
+    host_version: String,
+    /// Services of the host, handed to libraries in their [`HostContext`].
+    services: Services,
+    /// Names of the plugins asked to catch panics of their functions.
+    panic_catching: std::collections::BTreeSet<String>,
+    /// Symbols of the entrypoints looked up in libraries, in order.
+    entrypoint_symbols: Vec<EntrypointSymbol>,
+    /// Are the signatures of libraries verified before opening them.
+    #[cfg(feature = "signature")]
+    signature_verification: bool,
+    /// Keys libraries must be signed with.
+    #[cfg(feature = "signature")]
+    trusted_keys: Vec<VerifyingKey>,
+    /// Digests libraries must match, if any.
+    #[cfg(feature = "pinning")]
+    digest_pins: Option<DigestPins>,
+    /// Flags passed to `dlopen`.
+    #[cfg(target_os = "linux")]
+    dlopen_options: DlopenOptions,
+    /// Link-map namespace libraries are opened in.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    link_namespace: LinkNamespace,
+    /// Link-map namespaces created for groups of libraries, by group name.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    namespaces: std::collections::BTreeMap<String, libc::Lmid_t>,
+}
+
+/// Marker identifying the type iterator that keeps a library alive, see [`set_library_guard`].
+struct LibraryGuard;
+
+/// A library opened by the loader.
+struct OpenLibrary {
+    library: rhai::Shared<libloading::Library>,
+    /// Anonymous memory file the library was opened from, closed once the library is closed.
+    memory_file: Option<std::fs::File>,
+    /// Group of the link-map namespace the library was opened in, see [`LinkNamespace::Group`].
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    group: Option<String>,
+}
+
+/// Modules of a library to build.
+#[derive(Debug, Clone, Copy)]
+enum Selection<'a> {
+    /// The module built by the entrypoint, or all the modules of the registry if there is no entrypoint.
+    Entrypoint,
+    /// One of the modules of the registry.
+    Named(&'a str),
+    /// All the modules of the registry, as sub-modules of a single module.
+    All,
+}
+
+/// Error returned when a library cannot be unloaded.
+#[derive(Debug)]
+pub enum UnloadError {
+    /// No library is loaded with this handle.
+    UnknownHandle(PluginHandle),
+    /// Modules produced by the library are still alive.
+    InUse {
+        /// Handle of the library.
+        handle: PluginHandle,
+        /// Number of functions and engines keeping the library alive.
+        references: usize,
+    },
+    /// The library could not be closed.
+    Close(libloading::Error),
+}
+
+impl std::fmt::Display for UnloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownHandle(handle) => {
+                write!(f, "no library loaded with handle {}", handle.value())
+            }
+            Self::InUse { handle, references } => write!(
+                f,
+                "library {} is still in use by {references} function(s) or engine(s)",
+                handle.value()
+            ),
+            Self::Close(error) => write!(f, "failed to close library: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for UnloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Close(error) => Some(error),
+            Self::UnknownHandle(_) | Self::InUse { .. } => None,
+        }
+    }
+}
+
+/// Error returned when a library cannot be loaded.
+///
+/// It converts into a [`rhai::EvalAltResult::ErrorInModule`] error holding it, see [`Loader::load`].
+#[derive(Debug)]
+pub enum LoadError {
+    /// The library could not be read, for example because it does not exist, or copied.
+    Io {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// The library is not signed by a trusted key, see [`Libloading::enable_signature_verification`].
+    #[cfg(feature = "signature")]
+    Signature {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// Why the signature was rejected.
+        source: SignatureError,
+    },
+    /// The digest of the library does not match the pins of the host, see [`Libloading::set_digest_pins`].
+    #[cfg(feature = "pinning")]
+    Pinning {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// Why the library was refused.
+        source: PinError,
+    },
+    /// The library could not be opened, for example because it is not a dynamic library
+    /// or was built for another platform.
+    Open {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: libloading::Error,
+    },
+    /// The library could not be opened in its own namespace, or does not use the C ABI, see [`LinkNamespace`].
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    Dlmopen {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The error reported by `dlmopen`.
+        message: String,
+    },
+    /// The library does not export a symbol required by the loader, it is likely not a plugin.
+    MissingSymbol {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// Name of the symbol.
+        symbol: String,
+        /// The underlying error.
+        source: libloading::Error,
+    },
+    /// The library exports none of the entrypoints looked up by the loader.
+    NoEntrypoint {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// Symbols looked up, in order.
+        symbols: Vec<String>,
+    },
+    /// The library uses the C ABI, and only exports a single module, not a registry of modules.
+    NoRegistry {
+        /// Path of the library.
+        path: std::path::PathBuf,
+    },
+    /// The library was built in an environment incompatible with the host.
+    Incompatible {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The differences found.
+        source: IncompatiblePlugin,
+    },
+    /// The type ids of core Rhai types differ between the library and the host.
+    TypeIds {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The differences found.
+        source: TypeIdMismatch,
+    },
+    /// The library hashes functions differently from the host, see [`Libloading::set_hashing_policy`].
+    Hashing {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The seeds and fingerprints of both.
+        source: HashingMismatch,
+    },
+    /// The library failed to build its module or to register items in the engine.
+    Plugin {
+        /// Path of the library.
+        path: std::path::PathBuf,
+        /// The error reported by the library.
+        message: String,
+    },
+}
+
+impl LoadError {
+    /// Path of the library that could not be loaded.
+    #[must_use]
+    pub fn path(&self) -> &std::path::Path {
+        match self {
+            Self::Io { path, .. }
+            | Self::Open { path, .. }
+            | Self::MissingSymbol { path, .. }
+            | Self::NoEntrypoint { path, .. }
+            | Self::NoRegistry { path }
+            | Self::Incompatible { path, .. }
+            | Self::TypeIds { path, .. }
+            | Self::Hashing { path, .. }
+            | Self::Plugin { path, .. } => path,
+            #[cfg(feature = "signature")]
+            Self::Signature { path, .. } => path,
+            #[cfg(feature = "pinning")]
+            Self::Pinning { path, .. } => path,
+            #[cfg(all(target_os = "linux", target_env = "gnu"))]
+            Self::Dlmopen { path, .. } => path,
+        }
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { source, .. } => write!(f, "failed to read library: {source}"),
+            #[cfg(feature = "signature")]
+            Self::Signature { source, .. } => write!(f, "{source}"),
+            #[cfg(feature = "pinning")]
+            Self::Pinning { source, .. } => write!(f, "{source}"),
+            Self::Open { source, .. } => write!(f, "failed to open library: {source}"),
+            #[cfg(all(target_os = "linux", target_env = "gnu"))]
+            Self::Dlmopen { message, .. } => write!(f, "failed to open library: {message}"),
+            Self::MissingSymbol { symbol, source, .. } => {
+                write!(f, "failed to find `{symbol}`: {source}")
+            }
+            Self::NoEntrypoint { symbols, .. } => {
+                write!(f, "failed to find ")?;
+
+                for (index, symbol) in symbols.iter().enumerate() {
+                    let separator = match index {
+                        0 => "",
+                        _ if index + 1 == symbols.len() => " or ",
+                        _ => ", ",
+                    };
+                    write!(f, "{separator}`{symbol}`")?;
+                }
+
+                Ok(())
+            }
+            Self::NoRegistry { .. } => {
+                write!(f, "libraries using the C ABI export a single module")
+            }
+            Self::Incompatible { source, .. } => write!(f, "{source}"),
+            Self::TypeIds { source, .. } => write!(f, "{source}"),
+            Self::Hashing { source, .. } => write!(f, "{source}"),
+            Self::Plugin { message, .. } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            #[cfg(feature = "signature")]
+            Self::Signature { source, .. } => Some(source),
+            #[cfg(feature = "pinning")]
+            Self::Pinning { source, .. } => Some(source),
+            Self::Open { source, .. } | Self::MissingSymbol { source, .. } => Some(source),
+            Self::Incompatible { source, .. } => Some(source),
+            Self::TypeIds { source, .. } => Some(source),
+            Self::Hashing { source, .. } => Some(source),
+            Self::NoEntrypoint { .. } | Self::NoRegistry { .. } | Self::Plugin { .. } => None,
+            #[cfg(all(target_os = "linux", target_env = "gnu"))]
+            Self::Dlmopen { .. } => None,
+        }
+    }
+}
+
+impl From<LoadError> for rhai::EvalAltResult {
+    fn from(error: LoadError) -> Self {
+        let path = error
+            .path()
+            .to_str()
+            .map_or(String::default(), std::string::ToString::to_string);
+
+        Self::ErrorInModule(
+            path,
+            Self::ErrorSystem(String::default(), Box::new(error)).into(),
+            rhai::Position::NONE,
+        )
+    }
+}
+
+impl From<LoadError> for Box<rhai::EvalAltResult> {
+    fn from(error: LoadError) -> Self {
+        rhai::EvalAltResult::from(error).into()
+    }
+}
+
+/// Symbol of an entrypoint building the module of a library, with its prototype.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntrypointSymbol {
+    /// An entrypoint following the [`Entrypoint`] prototype.
+    Entrypoint(String),
+    /// An entrypoint following the [`EntrypointV2`] prototype.
+    EntrypointV2(String),
+}
+
+impl EntrypointSymbol {
+    /// Name of the symbol.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Entrypoint(name) | Self::EntrypointV2(name) => name,
+        }
+    }
+
+    /// Symbols looked up by default: [`MODULE_ENTRYPOINT_V2`], then [`MODULE_ENTRYPOINT`].
+    #[must_use]
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::EntrypointV2(MODULE_ENTRYPOINT_V2.to_string()),
+            Self::Entrypoint(MODULE_ENTRYPOINT.to_string()),
+        ]
+    }
+}
+
+/// How the symbols of a library are resolved when it is opened, see [`DlopenOptions`].
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymbolBinding {
+    /// Resolve all the symbols when the library is opened (`RTLD_NOW`), failing early if one is missing.
+    #[default]
+    Now,
+    /// Resolve functions when they are first called (`RTLD_LAZY`), opening the library faster.
+    Lazy,
+}
+
+/// Which libraries can use the symbols of a library, see [`DlopenOptions`].
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymbolVisibility {
+    /// Only the library itself (`RTLD_LOCAL`).
+    #[default]
+    Local,
+    /// Libraries opened afterwards too (`RTLD_GLOBAL`), for plugins sharing symbols.
+    Global,
+}
+
+/// Flags passed to `dlopen` when opening libraries on Linux.
+///
+/// The default, `RTLD_NOW | RTLD_LOCAL | RTLD_NODELETE`, keeps the code of libraries mapped once they are closed:
+/// thread-local destructors registered by a library can run after it is unloaded, see
+/// <https://github.com/nagisa/rust_libloading/issues/5#issuecomment-244195096>.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DlopenOptions {
+    /// When symbols are resolved.
+    binding: SymbolBinding,
+    /// Who can use the symbols.
+    visibility: SymbolVisibility,
+    /// Is the code of a library kept mapped once it is closed.
+    no_delete: bool,
+    /// Does a library prefer its own symbols over the global ones.
+    deep_bind: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl Default for DlopenOptions {
+    fn default() -> Self {
+        Self {
+            binding: SymbolBinding::default(),
+            visibility: SymbolVisibility::default(),
+            no_delete: true,
+            deep_bind: false,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DlopenOptions {
+    /// Create the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set when the symbols of libraries are resolved.
+    pub fn set_binding(&mut self, binding: SymbolBinding) -> &mut Self {
+        self.binding = binding;
+        self
+    }
+
+    /// When the symbols of libraries are resolved.
+    #[must_use]
+    pub const fn binding(&self) -> SymbolBinding {
+        self.binding
+    }
+
+    /// Set which libraries can use the symbols of libraries.
+    pub fn set_visibility(&mut self, visibility: SymbolVisibility) -> &mut Self {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Which libraries can use the symbols of libraries.
+    #[must_use]
+    pub const fn visibility(&self) -> SymbolVisibility {
+        self.visibility
+    }
+
+    /// Enable/disable keeping the code of libraries mapped once they are closed (`RTLD_NODELETE`).
+    ///
+    /// Disabling it lets [`Libloading::unload`] really unload libraries, which must then not
+    /// register thread-local destructors.
+    pub fn enable_no_delete(&mut self, enable: bool) -> &mut Self {
+        self.no_delete = enable;
+        self
+    }
+
+    /// Is the code of libraries kept mapped once they are closed?
+    #[must_use]
+    pub const fn is_no_delete_enabled(&self) -> bool {
+        self.no_delete
+    }
+
+    /// Enable/disable making libraries use their own symbols before the global ones (`RTLD_DEEPBIND`).
+    ///
+    /// Only supported by glibc, ignored by other C libraries.
+    pub fn enable_deep_bind(&mut self, enable: bool) -> &mut Self {
+        self.deep_bind = enable;
+        self
+    }
+
+    /// Do libraries use their own symbols before the global ones?
+    #[must_use]
+    pub const fn is_deep_bind_enabled(&self) -> bool {
+        self.deep_bind
+    }
+
+    /// The flags passed to `dlopen`.
+    #[must_use]
+    pub fn flags(&self) -> std::os::raw::c_int {
+        let mut flags = match self.binding {
+            SymbolBinding::Now => libc::RTLD_NOW,
+            SymbolBinding::Lazy => libc::RTLD_LAZY,
+        };
+
+        flags |= match self.visibility {
+            SymbolVisibility::Local => libc::RTLD_LOCAL,
+            SymbolVisibility::Global => libc::RTLD_GLOBAL,
+        };
+
+        if self.no_delete {
+            flags |= libc::RTLD_NODELETE;
+        }
+
+        #[cfg(target_env = "gnu")]
+        if self.deep_bind {
+            flags |= libc::RTLD_DEEPBIND;
+        }
+
+        flags
+    }
+}
+
+/// Link-map namespace libraries are opened in, see [`Libloading::set_link_namespace`].
+///
+/// A library opened in its own namespace, with `dlmopen`, gets its own copy of the libraries it depends on,
+/// so that plugins depending on different versions of the same C library can coexist in the host.
+/// The C library itself is loaded again in each namespace, and glibc supports at most 16 namespaces.
+///
+/// Only libraries using the C ABI, see [`crate::plugin::ffi`], can be opened in their own namespace:
+/// the copy of the C library of a namespace comes with its own allocator, while libraries using the Rust ABI
+/// hand memory they allocated to the host. Such libraries are refused before being opened, by reading
+/// their dynamic symbols.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LinkNamespace {
+    /// The namespace of the host, libraries are opened with `dlopen`.
+    #[default]
+    Shared,
+    /// A new namespace for each library.
+    Isolated,
+    /// A namespace shared by the libraries opened with the same group name, created by the first of them.
+    ///
+    /// The namespace is kept as long as one of its libraries is loaded, libraries are never unloaded
+    /// unless [`DlopenOptions::enable_no_delete`] is disabled. Once all its libraries are unloaded with
+    /// [`Libloading::unload`], the next library of the group opens a new namespace.
+    Group(String),
+}
+
+/// What to do when a library hashes functions differently from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashingPolicy {
+    /// Refuse to load the library.
+    #[default]
+    Deny,
+    /// Load the library anyway, with a [`LoadWarning::Hashing`] in [`LoadedPlugin::warnings`].
+    Warn,
+    /// Do not compare hashing configurations.
+    Ignore,
+}
+
+/// What to do when the type ids of core Rhai types differ between a library and the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TypeIdPolicy {
+    /// Refuse to load the library.
+    #[default]
+    Deny,
+    /// Load the library anyway, with a [`LoadWarning::TypeIds`] in [`LoadedPlugin::warnings`].
+    Warn,
+    /// Do not compare type ids.
+    Ignore,
 }
 
 impl Default for Libloading {
     /// Create a new instance of the loader.
     fn default() -> Self {
-        Self { libraries: vec![] }
+        Self {
+            libraries: std::collections::BTreeMap::new(),
+            next_handle: 0,
+            compatibility_check: true,
+            hashing_seed_sharing: true,
+            hashing_policy: HashingPolicy::default(),
+            type_id_policy: TypeIdPolicy::default(),
+            shadow_copy: false,
+            shadow_copy_directory: default_shadow_copy_directory(),
+            host_name: String::new(),
+            host_version: String::new(),
+            services: Services::new(),
+            panic_catching: std::collections::BTreeSet::new(),
+            entrypoint_symbols: EntrypointSymbol::defaults(),
+            #[cfg(feature = "signature")]
+            signature_verification: false,
+            #[cfg(feature = "signature")]
+            trusted_keys: Vec::new(),
+            #[cfg(feature = "pinning")]
+            digest_pins: None,
+            #[cfg(target_os = "linux")]
+            dlopen_options: DlopenOptions::default(),
+            #[cfg(all(target_os = "linux", target_env = "gnu"))]
+            link_namespace: LinkNamespace::default(),
+            #[cfg(all(target_os = "linux", target_env = "gnu"))]
+            namespaces: std::collections::BTreeMap::new(),
+        }
     }
 }
 
@@ -81,108 +670,2668 @@ impl Libloading {
     pub fn new() -> Self {
         Self::default()
     }
-}
 
-impl Loader for Libloading {
-    /// Load a rhai module from a dynamic library.
-    fn load(
+    /// Enable/disable the compatibility check of libraries.
+    ///
+    /// Disabling the check allows loading libraries that do not export a compatibility record,
+    /// at the risk of undefined behavior if they were built in a different environment.
+    pub fn enable_compatibility_check(&mut self, enable: bool) -> &mut Self {
+        self.compatibility_check = enable;
+        self
+    }
+
+    /// Is the compatibility check enabled?
+    #[must_use]
+    pub const fn is_compatibility_check_enabled(&self) -> bool {
+        self.compatibility_check
+    }
+
+    /// Enable/disable handing the hashing seed of the host to libraries.
+    ///
+    /// When enabled, libraries build their module with the seed of the host, so they do not need
+    /// to call `rhai::config::hashing::set_hashing_seed` themselves. The host must have a seed set.
+    pub fn enable_hashing_seed_sharing(&mut self, enable: bool) -> &mut Self {
+        self.hashing_seed_sharing = enable;
+        self
+    }
+
+    /// Is the hashing seed of the host handed to libraries?
+    #[must_use]
+    pub const fn is_hashing_seed_sharing_enabled(&self) -> bool {
+        self.hashing_seed_sharing
+    }
+
+    /// Set what to do when a library hashes functions differently from the host.
+    ///
+    /// The functions of the module of such libraries are hashed again by the host, but the libraries can
+    /// fail to call functions through Rhai. [`HashingPolicy::Ignore`] allows loading libraries that do not
+    /// export a hashing record.
+    pub fn set_hashing_policy(&mut self, policy: HashingPolicy) -> &mut Self {
+        self.hashing_policy = policy;
+        self
+    }
+
+    /// What is done when a library hashes functions differently from the host.
+    #[must_use]
+    pub const fn hashing_policy(&self) -> HashingPolicy {
+        self.hashing_policy
+    }
+
+    /// Set what to do when the type ids of core Rhai types differ between a library and the host.
+    pub fn set_type_id_policy(&mut self, policy: TypeIdPolicy) -> &mut Self {
+        self.type_id_policy = policy;
+        self
+    }
+
+    /// What is done when the type ids of core Rhai types differ between a library and the host.
+    #[must_use]
+    pub const fn type_id_policy(&self) -> TypeIdPolicy {
+        self.type_id_policy
+    }
+
+    /// Enable/disable loading libraries from a copy.
+    ///
+    /// When enabled, each library is copied in [`Self::shadow_copy_directory`] under a name derived
+    /// from its content hash, and the copy is loaded instead. The original file can then be
+    /// overwritten, by a new build for example, while the host is running.
+    ///
+    /// Copies are not deleted when libraries are unloaded: a copy is shared by every loader of the same
+    /// library, in this process or another, and stays mapped while the library is kept loaded, with
+    /// `RTLD_NODELETE` for example. The directory keeps one copy per build, and can be emptied by the host
+    /// when no library is loaded from it, when it starts for example.
+    pub fn enable_shadow_copy(&mut self, enable: bool) -> &mut Self {
+        self.shadow_copy = enable;
+        self
+    }
+
+    /// Are libraries loaded from a copy?
+    #[must_use]
+    pub const fn is_shadow_copy_enabled(&self) -> bool {
+        self.shadow_copy
+    }
+
+    /// Set the directory where libraries are copied, defaults to `rhai-dylib-<user id>` in the temporary
+    /// directory, `rhai-dylib` on Windows.
+    ///
+    /// The directory is created if needed. On Unix, libraries are only copied in a directory owned by
+    /// the current user that no one else can write in.
+    pub fn set_shadow_copy_directory(
         &mut self,
-        path: impl AsRef<std::path::Path>,
-    ) -> Result<rhai::Shared<rhai::Module>, Box<rhai::EvalAltResult>> {
-        let library = unsafe {
-            #[cfg(target_os = "linux")]
-            {
-                // Workaround for a crash on library unloading on linux: https://github.com/nagisa/rust_libloading/issues/5#issuecomment-244195096
-                libloading::os::unix::Library::open(
-                    Some(path.as_ref()),
-                    // Load library with `RTLD_NOW | RTLD_NODELETE` to fix SIGSEGV.
-                    0x2 | 0x1000,
-                )
-                .map(libloading::Library::from)
-            }
+        directory: impl Into<std::path::PathBuf>,
+    ) -> &mut Self {
+        self.shadow_copy_directory = directory.into();
+        self
+    }
 
-            #[cfg(any(target_os = "macos", target_os = "windows"))]
-            {
-                libloading::Library::new(path.as_ref())
-            }
+    /// The directory where libraries are copied.
+    #[must_use]
+    pub fn shadow_copy_directory(&self) -> &std::path::Path {
+        &self.shadow_copy_directory
+    }
+
+    /// Set the name of the host, handed to libraries in their [`HostContext`].
+    pub fn set_host_name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.host_name = name.into();
+        self
+    }
+
+    /// Set the version of the host, handed to libraries in their [`HostContext`].
+    pub fn set_host_version(&mut self, version: impl Into<String>) -> &mut Self {
+        self.host_version = version.into();
+        self
+    }
+
+    /// Services handed to libraries in their [`HostContext`].
+    #[must_use]
+    pub const fn services(&self) -> &Services {
+        &self.services
+    }
+
+    /// Services handed to libraries in their [`HostContext`], to register new ones.
+    pub fn services_mut(&mut self) -> &mut Services {
+        &mut self.services
+    }
+
+    /// Enable/disable catching panics in the functions of the plugin named `plugin`, see [`crate::plugin::panics`].
+    ///
+    /// When enabled, a function panicking during a script call returns a [`rhai::EvalAltResult::ErrorRuntime`]
+    /// error naming the plugin and the function instead of unwinding into the host. Each function call
+    /// is slightly slower. Only plugins exporting a [`MODULE_ENTRYPOINT_V2`] entrypoint can honor it.
+    pub fn enable_panic_catching(&mut self, plugin: impl Into<String>, enable: bool) -> &mut Self {
+        let plugin = plugin.into();
+
+        if enable {
+            self.panic_catching.insert(plugin);
+        } else {
+            self.panic_catching.remove(&plugin);
         }
-        .map_err(|error| {
-            rhai::EvalAltResult::ErrorInModule(
-                path.as_ref()
-                    .to_str()
-                    .map_or(String::default(), std::string::ToString::to_string),
-                error.to_string().into(),
-                rhai::Position::NONE,
-            )
-        })?;
 
-        self.libraries.push(library);
-        let library = self.libraries.last().expect("library just got inserted");
+        self
+    }
 
-        let module_entrypoint = unsafe { library.get::<Entrypoint>(MODULE_ENTRYPOINT.as_bytes()) }
-            .map_err(|error| {
-                rhai::EvalAltResult::ErrorInModule(
-                    path.as_ref()
-                        .to_str()
-                        .map_or(String::default(), std::string::ToString::to_string),
-                    error.to_string().into(),
-                    rhai::Position::NONE,
-                )
-            })?;
+    /// Are panics caught in the functions of the plugin named `plugin`?
+    #[must_use]
+    pub fn is_panic_catching_enabled(&self, plugin: &str) -> bool {
+        self.panic_catching.contains(plugin)
+    }
 
-        Ok(module_entrypoint())
+    /// Set the symbols of the entrypoints looked up in libraries, tried in order until one is found,
+    /// defaults to [`EntrypointSymbol::defaults`].
+    ///
+    /// Libraries exporting none of them are loaded through their [`crate::plugin::registry::ModuleRegistry`], if any.
+    /// Libraries using the C ABI always export [`MODULE_FFI_ENTRYPOINT`].
+    pub fn set_entrypoint_symbols(
+        &mut self,
+        symbols: impl IntoIterator<Item = EntrypointSymbol>,
+    ) -> &mut Self {
+        self.entrypoint_symbols = symbols.into_iter().collect();
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::loader::Loader;
+    /// Symbols of the entrypoints looked up in libraries, in order.
+    #[must_use]
+    pub fn entrypoint_symbols(&self) -> &[EntrypointSymbol] {
+        &self.entrypoint_symbols
+    }
 
-    fn build_test_plugin() -> &'static std::path::PathBuf {
-        // Prevents multiple threads writing to the dll on windows and triggering a STATUS_ACCESS_VIOLATION error.
-        static PATH: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
-        PATH.get_or_init(|| {
-            let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
-            let status = std::process::Command::new("cargo")
-                .args(["build", "--example", "test_plugin"])
-                .current_dir(manifest_dir)
-                .status()
-                .expect("failed to execute cargo build");
+    /// Enable/disable verifying the signature of libraries before opening them, see [`crate::loader::signature`].
+    ///
+    /// When enabled, libraries that are not signed by one of the [`Self::trusted_keys`] are rejected.
+    /// The library is read once, and opened from the bytes that were verified, so that it cannot be replaced
+    /// in between: on Linux from a sealed memory file like [`Self::load_from_bytes`], elsewhere from a shadow
+    /// copy, see [`Self::set_shadow_copy_directory`].
+    #[cfg(feature = "signature")]
+    pub fn enable_signature_verification(&mut self, enable: bool) -> &mut Self {
+        self.signature_verification = enable;
+        self
+    }
 
-            assert!(status.success(), "building test_plugin failed");
+    /// Are the signatures of libraries verified before opening them?
+    #[cfg(feature = "signature")]
+    #[must_use]
+    pub const fn is_signature_verification_enabled(&self) -> bool {
+        self.signature_verification
+    }
 
-            let target_dir = std::env::var("CARGO_TARGET_DIR")
-                .map(std::path::PathBuf::from)
-                .unwrap_or_else(|_| manifest_dir.join("target"));
+    /// Trust libraries signed with `key`.
+    #[cfg(feature = "signature")]
+    pub fn add_trusted_key(&mut self, key: VerifyingKey) -> &mut Self {
+        self.trusted_keys.push(key);
+        self
+    }
 
-            #[cfg(target_os = "linux")]
-            return target_dir.join("debug/examples/libtest_plugin.so");
-            #[cfg(target_os = "macos")]
-            return target_dir.join("debug/examples/libtest_plugin.dylib");
-            #[cfg(target_os = "windows")]
-            return target_dir.join("debug/examples/test_plugin.dll");
-        })
+    /// Keys libraries must be signed with when signatures are verified.
+    #[cfg(feature = "signature")]
+    #[must_use]
+    pub fn trusted_keys(&self) -> &[VerifyingKey] {
+        &self.trusted_keys
     }
 
-    #[test]
-    fn new() {
-        let _ = Libloading::new();
+    /// Set the digests libraries must match to be opened, see [`crate::loader::pinning`], or `None` to accept any library.
+    ///
+    /// Like signatures, see [`Self::enable_signature_verification`], libraries are opened from the bytes
+    /// that were checked. Libraries loaded with [`Self::load_from_bytes`] are checked under the name
+    /// they are loaded with.
+    #[cfg(feature = "pinning")]
+    pub fn set_digest_pins(&mut self, pins: Option<DigestPins>) -> &mut Self {
+        self.digest_pins = pins;
+        self
     }
 
-    #[test]
-    fn load_success() {
-        let mut loader = Libloading::new();
-        loader
-            .load(build_test_plugin().as_path())
-            .expect("failed to load test_plugin");
+    /// The digests libraries must match to be opened, if any.
+    #[cfg(feature = "pinning")]
+    #[must_use]
+    pub const fn digest_pins(&self) -> Option<&DigestPins> {
+        self.digest_pins.as_ref()
     }
 
-    #[test]
-    fn load_nonexistent_returns_error() {
-        let mut loader = Libloading::new();
-        let err = loader.load("nonexistent.so").unwrap_err();
+    /// Set the flags passed to `dlopen` when opening libraries.
+    #[cfg(target_os = "linux")]
+    pub fn set_dlopen_options(&mut self, options: DlopenOptions) -> &mut Self {
+        self.dlopen_options = options;
+        self
+    }
 
-        assert!(matches!(*err, rhai::EvalAltResult::ErrorInModule(..)));
+    /// The flags passed to `dlopen` when opening libraries.
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub const fn dlopen_options(&self) -> DlopenOptions {
+        self.dlopen_options
+    }
+
+    /// Set the link-map namespace the next libraries are opened in.
+    ///
+    /// Libraries are opened in the namespace of the host by default. To open groups of libraries in
+    /// different namespaces, change the namespace between loads.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    pub fn set_link_namespace(&mut self, namespace: LinkNamespace) -> &mut Self {
+        self.link_namespace = namespace;
+        self
+    }
+
+    /// The link-map namespace the next libraries are opened in.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[must_use]
+    pub const fn link_namespace(&self) -> &LinkNamespace {
+        &self.link_namespace
+    }
+
+    /// Unload the library identified by `handle`.
+    ///
+    /// The library is refused to be unloaded while the module it produced, or a module containing
+    /// its functions, is still alive, for example in a [`rhai::Engine`] or a module resolver cache.
+    ///
+    /// On Linux, the code of the library is only unmapped if it was opened with [`DlopenOptions::enable_no_delete`]
+    /// disabled. With the default options, unloading succeeds but the library stays resident: its code and
+    /// static data are kept, and loading it again reuses them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no library is loaded with `handle`, if the module of the library
+    /// is still alive or if the library could not be closed.
+    pub fn unload(&mut self, handle: PluginHandle) -> Result<(), UnloadError> {
+        let std::collections::btree_map::Entry::Occupied(loaded) = self.libraries.entry(handle)
+        else {
+            return Err(UnloadError::UnknownHandle(handle));
+        };
+
+        let references = rhai::Shared::strong_count(&loaded.get().library) - 1;
+
+        if references != 0 {
+            return Err(UnloadError::InUse { handle, references });
+        }
+
+        let loaded = loaded.remove();
+
+        // The namespace of a group can be deleted with its last library, the next library of the group opens a new one.
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        if let Some(group) = &loaded.group {
+            if !self
+                .libraries
+                .values()
+                .any(|library| library.group.as_ref() == Some(group))
+            {
+                self.namespaces.remove(group);
+            }
+        }
+
+        let OpenLibrary {
+            library,
+            memory_file,
+            ..
+        } = loaded;
+
+        let closed = rhai::Shared::into_inner(library).map_or(Ok(()), |library| {
+            library.close().map_err(UnloadError::Close)
+        });
+        drop(memory_file);
+
+        closed
+    }
+
+    /// Copy `content`, the library at `path`, in the shadow copy directory, unless an identical copy already exists.
+    fn shadow_copy(
+        &self,
+        path: &std::path::Path,
+        content: &[u8],
+    ) -> std::io::Result<std::path::PathBuf> {
+        // Not a cryptographic digest: an existing copy is only reused if it has the same content.
+        let hash = {
+            use std::hash::{Hash, Hasher};
+
+            let mut hasher = std::hash::DefaultHasher::new();
+            content.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut copy = self.shadow_copy_directory.join(format!(
+            "{}-{hash:016x}",
+            path.file_stem().unwrap_or_default().to_string_lossy()
+        ));
+        if let Some(extension) = path.extension() {
+            copy.set_extension(extension);
+        }
+
+        create_private_directory(&self.shadow_copy_directory)?;
+
+        // An existing copy is reused only if it was not modified since it was written.
+        if std::fs::read(&copy).is_ok_and(|existing| existing == content) {
+            return Ok(copy);
+        }
+
+        // Write then rename, so that a partial copy is never loaded. Loaders of the same process
+        // can copy the same library at once, each partial copy gets its own name.
+        let partial = copy.with_extension(format!(
+            "partial-{}-{}",
+            std::process::id(),
+            PARTIAL_COPIES.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        match std::fs::remove_file(&partial) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+
+        let written = std::fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&partial)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, content))
+            .and_then(|()| std::fs::rename(&partial, &copy));
+
+        if written.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+
+        written.map(|()| copy)
+    }
+
+    /// Are libraries checked before they are opened, see [`Self::check_library`]?
+    #[cfg(any(feature = "signature", feature = "pinning"))]
+    fn checks_libraries(&self) -> bool {
+        #[cfg(feature = "signature")]
+        if self.signature_verification {
+            return true;
+        }
+
+        #[cfg(feature = "pinning")]
+        if self.digest_pins.is_some() {
+            return true;
+        }
+
+        false
+    }
+
+    /// Verify the signature of the content of the library at `path`, see [`crate::loader::signature::verify`],
+    /// then check its digest against the pins of the host, under the file name `name`.
+    ///
+    /// The detached signature next to `path` is only looked up if `detached` is set.
+    #[cfg(any(feature = "signature", feature = "pinning"))]
+    #[cfg_attr(
+        not(all(feature = "signature", feature = "pinning")),
+        allow(unused_variables)
+    )]
+    fn check_library(
+        &self,
+        path: &std::path::Path,
+        name: &str,
+        library: &[u8],
+        detached: bool,
+    ) -> Result<(), LoadError> {
+        #[cfg(feature = "signature")]
+        if self.signature_verification {
+            let detached = detached.then(|| super::signature::detached_signature_path(path));
+
+            super::signature::verify(&self.trusted_keys, library, detached.as_deref()).map_err(
+                |source| LoadError::Signature {
+                    path: path.into(),
+                    source,
+                },
+            )?;
+        }
+
+        #[cfg(feature = "pinning")]
+        if let Some(pins) = &self.digest_pins {
+            pins.check(name, library)
+                .map_err(|source| LoadError::Pinning {
+                    path: path.into(),
+                    source,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Open the library at `library_path`, the shadow copy of `path` if enabled.
+    fn open_library(
+        &mut self,
+        path: &std::path::Path,
+        library_path: &std::path::Path,
+    ) -> Result<libloading::Library, LoadError> {
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        if self.link_namespace != LinkNamespace::Shared {
+            return self.open_library_in_namespace(path, library_path);
+        }
+
+        unsafe {
+            #[cfg(target_os = "linux")]
+            {
+                libloading::os::unix::Library::open(Some(library_path), self.dlopen_options.flags())
+                    .map(libloading::Library::from)
+            }
+
+            #[cfg(any(target_os = "macos", target_os = "windows"))]
+            {
+                libloading::Library::new(library_path)
+            }
+        }
+        .map_err(|source| {
+            open_error(
+                library_path,
+                LoadError::Open {
+                    path: path.into(),
+                    source,
+                },
+            )
+        })
+    }
+
+    /// Open the library at `library_path` with `dlmopen`, in the namespace selected by [`Self::link_namespace`].
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn open_library_in_namespace(
+        &mut self,
+        path: &std::path::Path,
+        library_path: &std::path::Path,
+    ) -> Result<libloading::Library, LoadError> {
+        // Opening a library runs its initializers and can create a namespace that is never deleted,
+        // libraries using the Rust ABI are refused before when their symbols can be read.
+        if std::fs::read(library_path)
+            .ok()
+            .and_then(|library| defines_dynamic_symbol(&library, MODULE_FFI_ENTRYPOINT))
+            == Some(false)
+        {
+            return Err(rust_abi_in_namespace(path));
+        }
+
+        let namespace = match &self.link_namespace {
+            LinkNamespace::Shared => libc::LM_ID_BASE,
+            LinkNamespace::Isolated => libc::LM_ID_NEWLM,
+            LinkNamespace::Group(group) => self
+                .namespaces
+                .get(group)
+                .copied()
+                .unwrap_or(libc::LM_ID_NEWLM),
+        };
+
+        let (library, namespace) =
+            unsafe { dlmopen(namespace, library_path, self.dlopen_options.flags()) }.map_err(
+                |message| {
+                    open_error(
+                        library_path,
+                        LoadError::Dlmopen {
+                            path: path.into(),
+                            message,
+                        },
+                    )
+                },
+            )?;
+
+        if let LinkNamespace::Group(group) = &self.link_namespace {
+            self.namespaces.entry(group.clone()).or_insert(namespace);
+        }
+
+        Ok(library)
+    }
+
+    /// Check a library using the Rust ABI, then build its module.
+    fn build_module(
+        &self,
+        library: &libloading::Library,
+        path: &std::path::Path,
+        config: rhai::Map,
+        selection: Selection,
+        warnings: &mut Vec<LoadWarning>,
+    ) -> Result<(rhai::Shared<rhai::Module>, PluginMetadata, HostContext), LoadError> {
+        if self.compatibility_check {
+            Self::check_compatibility(library, path)?;
+        }
+
+        self.check_type_ids(library, path, warnings)?;
+
+        if self.hashing_seed_sharing {
+            Self::share_hashing_seed(library);
+        }
+
+        let metadata = read_metadata(library, path);
+
+        let mut context = HostContext::new(
+            self.host_name.clone(),
+            self.host_version.clone(),
+            config,
+            self.services.clone(),
+        );
+        context.enable_panic_catching(self.is_panic_catching_enabled(&metadata.name));
+
+        let module = self.call_entrypoint(library, path, &context, selection)?;
+
+        // The seed can be set by the entrypoint, the check can only happen afterwards.
+        self.check_hashing(library, path, warnings)?;
+
+        // Functions are hashed again by the host when the module is made to hold the library.
+        Ok((module, metadata, context))
+    }
+
+    /// Build the module of a library, with the context of the host if the library accepts it.
+    fn call_entrypoint(
+        &self,
+        library: &libloading::Library,
+        path: &std::path::Path,
+        context: &HostContext,
+        selection: Selection,
+    ) -> Result<rhai::Shared<rhai::Module>, LoadError> {
+        match selection {
+            Selection::Named(name) => {
+                return Self::call_named_entrypoint(library, path, context, name);
+            }
+            Selection::All => return Self::call_named_entrypoints(library, path, context),
+            Selection::Entrypoint => {}
+        }
+
+        for symbol in &self.entrypoint_symbols {
+            match symbol {
+                EntrypointSymbol::EntrypointV2(name) => {
+                    if let Ok(module_entrypoint) =
+                        unsafe { library.get::<EntrypointV2>(name.as_bytes()) }
+                    {
+                        return module_entrypoint(context)
+                            .map_err(|message| plugin_error(path, message));
+                    }
+                }
+                EntrypointSymbol::Entrypoint(name) => {
+                    if let Ok(module_entrypoint) =
+                        unsafe { library.get::<Entrypoint>(name.as_bytes()) }
+                    {
+                        return Ok(module_entrypoint());
+                    }
+                }
+            }
+        }
+
+        if unsafe { library.get::<ModuleNamesFn>(MODULE_NAMES.as_bytes()) }.is_ok() {
+            return Self::call_named_entrypoints(library, path, context);
+        }
+
+        Err(LoadError::NoEntrypoint {
+            path: path.into(),
+            symbols: self
+                .entrypoint_symbols
+                .iter()
+                .map(|symbol| symbol.name().to_string())
+                .chain(std::iter::once(MODULE_NAMES.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Build the module named `name` of the registry of a library.
+    fn call_named_entrypoint(
+        library: &libloading::Library,
+        path: &std::path::Path,
+        context: &HostContext,
+        name: &str,
+    ) -> Result<rhai::Shared<rhai::Module>, LoadError> {
+        let named_entrypoint =
+            unsafe { library.get::<NamedEntrypoint>(MODULE_NAMED_ENTRYPOINT.as_bytes()) }
+                .map_err(|error| missing_symbol(path, MODULE_NAMED_ENTRYPOINT, error))?;
+
+        named_entrypoint(name, context).map_err(|message| plugin_error(path, message))
+    }
+
+    /// Build all the modules of the registry of a library, as sub-modules of a single module.
+    fn call_named_entrypoints(
+        library: &libloading::Library,
+        path: &std::path::Path,
+        context: &HostContext,
+    ) -> Result<rhai::Shared<rhai::Module>, LoadError> {
+        let module_names = unsafe { library.get::<ModuleNamesFn>(MODULE_NAMES.as_bytes()) }
+            .map_err(|error| missing_symbol(path, MODULE_NAMES, error))?;
+
+        let mut module = rhai::Module::new();
+
+        for name in module_names().map_err(|message| plugin_error(path, message))? {
+            let sub_module = Self::call_named_entrypoint(library, path, context, &name)?;
+            module.set_sub_module(name, sub_module);
+        }
+
+        Ok(module.into())
+    }
+
+    /// Compare the compatibility record of a library with the one of the host.
+    fn check_compatibility(
+        library: &libloading::Library,
+        path: &std::path::Path,
+    ) -> Result<(), LoadError> {
+        let plugin = read_record::<*const Compatibility>(library, path, MODULE_COMPATIBILITY)?;
+
+        // SAFETY: plugins export their record with `Compatibility::record`, which starts with its version.
+        unsafe { Compatibility::current().check(plugin) }.map_err(|source| {
+            LoadError::Incompatible {
+                path: path.into(),
+                source,
+            }
+        })
+    }
+
+    /// Compare the type ids record of a library with the one of the host.
+    fn check_type_ids(
+        &self,
+        library: &libloading::Library,
+        path: &std::path::Path,
+        warnings: &mut Vec<LoadWarning>,
+    ) -> Result<(), LoadError> {
+        if self.type_id_policy == TypeIdPolicy::Ignore {
+            return Ok(());
+        }
+
+        let plugin = read_record::<TypeIds>(library, path, MODULE_TYPE_IDS)?;
+        let types = TypeIds::current().mismatches(&plugin);
+
+        if types.is_empty() {
+            return Ok(());
+        }
+
+        let error = TypeIdMismatch { types };
+
+        if self.type_id_policy == TypeIdPolicy::Warn {
+            warnings.push(LoadWarning::TypeIds(error));
+            Ok(())
+        } else {
+            Err(LoadError::TypeIds {
+                path: path.into(),
+                source: error,
+            })
+        }
+    }
+
+    /// Hand the hashing seed of the host to a library, so that its module is built with it.
+    fn share_hashing_seed(library: &libloading::Library) {
+        let Some(seed) = rhai::config::hashing::get_hashing_seed() else {
+            return;
+        };
+
+        // The module of libraries that cannot receive the seed is hashed again once built.
+        if let Ok(set_hashing_seed) =
+            unsafe { library.get::<SetHashingSeedFn>(MODULE_SET_HASHING_SEED.as_bytes()) }
+        {
+            set_hashing_seed(seed);
+        }
+    }
+
+    /// Compare the hashing record of a library with the one of the host.
+    fn check_hashing(
+        &self,
+        library: &libloading::Library,
+        path: &std::path::Path,
+        warnings: &mut Vec<LoadWarning>,
+    ) -> Result<(), LoadError> {
+        if self.hashing_policy == HashingPolicy::Ignore {
+            return Ok(());
+        }
+
+        let host = Hashing::current();
+        let plugin = read_record::<Hashing>(library, path, MODULE_HASHING)?;
+
+        if host.matches(&plugin) {
+            return Ok(());
+        }
+
+        let error = HashingMismatch::new(&host, &plugin);
+
+        if self.hashing_policy == HashingPolicy::Warn {
+            warnings.push(LoadWarning::Hashing(error));
+            Ok(())
+        } else {
+            Err(LoadError::Hashing {
+                path: path.into(),
+                source: error,
+            })
+        }
+    }
+}
+
+impl Libloading {
+    /// Load a rhai module from a dynamic library, handing `config` to the library.
+    ///
+    /// The configuration is part of the [`HostContext`] received by [`MODULE_ENTRYPOINT_V2`] entrypoints,
+    /// libraries only exporting [`MODULE_ENTRYPOINT`] do not receive it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the library cannot be opened, is not compatible with the host
+    /// or does not export an entrypoint.
+    pub fn load_with_config(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        config: rhai::Map,
+    ) -> Result<LoadedPlugin, LoadError> {
+        self.load_library(path.as_ref(), config, None, Selection::Entrypoint)
+    }
+
+    /// Load a rhai module from a dynamic library, and let the library register items in `engine`.
+    ///
+    /// Libraries exporting a [`MODULE_ENGINE_ENTRYPOINT`] can register types, operators, custom syntax
+    /// or callbacks in the engine. The engine keeps the library alive from then on.
+    /// The module of the library is returned, and is not registered in the engine.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the library cannot be opened, is not compatible with the host
+    /// or does not export an entrypoint.
+    pub fn load_with_engine(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        config: rhai::Map,
+        engine: &mut rhai::Engine,
+    ) -> Result<LoadedPlugin, LoadError> {
+        self.load_library(path.as_ref(), config, Some(engine), Selection::Entrypoint)
+    }
+
+    /// Load the module named `name` of the [`ModuleRegistry`](crate::plugin::registry::ModuleRegistry)
+    /// of a dynamic library, handing `config` to the library.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the library cannot be opened, is not compatible with the host,
+    /// does not export a registry or has no module named `name`.
+    pub fn load_module(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        name: &str,
+        config: rhai::Map,
+    ) -> Result<LoadedPlugin, LoadError> {
+        self.load_library(path.as_ref(), config, None, Selection::Named(name))
+    }
+
+    /// Load all the modules of the [`ModuleRegistry`](crate::plugin::registry::ModuleRegistry)
+    /// of a dynamic library, by name, handing `config` to the library.
+    ///
+    /// The modules share the handle of the library, which is only unloaded once all of them are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the library cannot be opened, is not compatible with the host,
+    /// does not export a registry or fails to build one of its modules.
+    pub fn load_modules(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        config: rhai::Map,
+    ) -> Result<std::collections::BTreeMap<String, LoadedPlugin>, LoadError> {
+        let plugin = self.load_library(path.as_ref(), config, None, Selection::All)?;
+
+        Ok(plugin
+            .module
+            .iter_sub_modules()
+            .map(|(name, module)| {
+                (
+                    name.to_string(),
+                    LoadedPlugin {
+                        handle: plugin.handle,
+                        module: module.clone(),
+                        metadata: plugin.metadata.clone(),
+                        warnings: plugin.warnings.clone(),
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Load a rhai module from a dynamic library held in memory, `name` standing for its path in errors.
+    ///
+    /// The library is written to an anonymous memory file sealed against writes, then opened from its
+    /// `/proc/self/fd` path: it never touches the disk, and cannot change once loaded. Shadow copies are not used.
+    ///
+    /// The memory file is closed once the library is unloaded with [`Self::unload`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the memory file cannot be created, if the library cannot be opened,
+    /// is not compatible with the host or does not export an entrypoint.
+    #[cfg(target_os = "linux")]
+    pub fn load_from_bytes(&mut self, name: &str, bytes: &[u8]) -> Result<LoadedPlugin, LoadError> {
+        let path = std::path::Path::new(name);
+
+        #[cfg(any(feature = "signature", feature = "pinning"))]
+        self.check_library(path, name, bytes, false)?;
+        self.load_checked(path, bytes, rhai::Map::new(), None, Selection::Entrypoint)
+    }
+
+    /// Load a library, letting it register items in `engine` if any.
+    fn load_library(
+        &mut self,
+        path: &std::path::Path,
+        config: rhai::Map,
+        engine: Option<&mut rhai::Engine>,
+        selection: Selection,
+    ) -> Result<LoadedPlugin, LoadError> {
+        let io_error = |source| LoadError::Io {
+            path: path.into(),
+            source,
+        };
+
+        // The library is opened from the bytes that were checked: the file could be replaced in between.
+        #[cfg(any(feature = "signature", feature = "pinning"))]
+        if self.checks_libraries() {
+            let library = std::fs::read(path).map_err(io_error)?;
+
+            self.check_library(
+                path,
+                &path.file_name().unwrap_or_default().to_string_lossy(),
+                &library,
+                true,
+            )?;
+
+            return self.load_checked(path, &library, config, engine, selection);
+        }
+
+        let shadow_copy = if self.shadow_copy {
+            let library = std::fs::read(path).map_err(io_error)?;
+            Some(self.shadow_copy(path, &library).map_err(io_error)?)
+        } else {
+            None
+        };
+
+        let library_path = shadow_copy.as_deref().unwrap_or(path);
+
+        self.load_library_at(path, library_path, config, engine, selection)
+    }
+
+    /// Load `library`, the checked content of the library at `path`.
+    ///
+    /// On Linux, the library is opened from an anonymous memory file sealed against writes, see
+    /// [`Self::load_from_bytes`]. Elsewhere, it is opened from a copy written in the shadow copy directory,
+    /// whether shadow copies are enabled or not.
+    #[cfg(any(target_os = "linux", feature = "signature", feature = "pinning"))]
+    fn load_checked(
+        &mut self,
+        path: &std::path::Path,
+        library: &[u8],
+        config: rhai::Map,
+        engine: Option<&mut rhai::Engine>,
+        selection: Selection,
+    ) -> Result<LoadedPlugin, LoadError> {
+        let io_error = |source| LoadError::Io {
+            path: path.into(),
+            source,
+        };
+
+        #[cfg(target_os = "linux")]
+        {
+            let file = memory_file(
+                &path.file_name().unwrap_or_default().to_string_lossy(),
+                library,
+            )
+            .map_err(io_error)?;
+            let (file, library_path) = self.memory_file_path(file).map_err(io_error)?;
+
+            let plugin = self.load_library_at(path, &library_path, config, engine, selection)?;
+
+            if let Some(library) = self.libraries.get_mut(&plugin.handle) {
+                library.memory_file = Some(file);
+            }
+
+            Ok(plugin)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let copy = self.shadow_copy(path, library).map_err(io_error)?;
+            self.load_library_at(path, &copy, config, engine, selection)
+        }
+    }
+
+    /// Get the path to open the memory file `file` from, which no library still loaded is named after.
+    ///
+    /// The dynamic linker identifies libraries by path first: a library kept loaded once its memory file was
+    /// closed, such as with `RTLD_NODELETE`, would be mistaken for the library of a new memory file given the
+    /// same descriptor. The memory file is then given another descriptor.
+    #[cfg(target_os = "linux")]
+    fn memory_file_path(
+        &self,
+        mut file: std::fs::File,
+    ) -> std::io::Result<(std::fs::File, std::path::PathBuf)> {
+        use std::os::fd::AsRawFd;
+
+        // Descriptors named like a loaded library are held until a free one is found.
+        let mut taken = vec![];
+
+        loop {
+            let path = std::path::PathBuf::from(format!("/proc/self/fd/{}", file.as_raw_fd()));
+
+            if !self.is_loaded(&path) {
+                return Ok((file, path));
+            }
+
+            let duplicate = file.try_clone()?;
+            taken.push(std::mem::replace(&mut file, duplicate));
+        }
+    }
+
+    /// Is a library named `path` loaded in the namespace the next library is opened in.
+    #[cfg(target_os = "linux")]
+    fn is_loaded(&self, path: &std::path::Path) -> bool {
+        let flags = libc::RTLD_LAZY | libc::RTLD_NOLOAD;
+
+        #[cfg(target_env = "gnu")]
+        {
+            let namespace = match &self.link_namespace {
+                LinkNamespace::Shared => libc::LM_ID_BASE,
+                LinkNamespace::Isolated => return false,
+                LinkNamespace::Group(group) => match self.namespaces.get(group) {
+                    Some(namespace) => *namespace,
+                    None => return false,
+                },
+            };
+
+            unsafe { dlmopen(namespace, path, flags) }.is_ok()
+        }
+
+        #[cfg(not(target_env = "gnu"))]
+        unsafe { libloading::os::unix::Library::open(Some(path), flags) }.is_ok()
+    }
+
+    /// Load the library at `library_path`, named `path` in errors.
+    fn load_library_at(
+        &mut self,
+        path: &std::path::Path,
+        library_path: &std::path::Path,
+        config: rhai::Map,
+        engine: Option<&mut rhai::Engine>,
+        selection: Selection,
+    ) -> Result<LoadedPlugin, LoadError> {
+        let library = self.open_library(path, library_path)?;
+
+        // Plugins using the C ABI share no Rust type with the host, the checks do not apply to them.
+        let ffi_entrypoint =
+            unsafe { library.get::<FfiEntrypoint>(MODULE_FFI_ENTRYPOINT.as_bytes()) }
+                .ok()
+                .map(|entrypoint| *entrypoint);
+
+        // Each namespace has its own allocator, the host would free memory allocated by a library using the Rust ABI.
+        // Such libraries are refused before being opened, unless their symbols could not be read.
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        if ffi_entrypoint.is_none() && self.link_namespace != LinkNamespace::Shared {
+            return Err(rust_abi_in_namespace(path));
+        }
+
+        let mut warnings = vec![];
+
+        let (module, metadata, context) = if let Some(entrypoint) = ffi_entrypoint {
+            if !matches!(selection, Selection::Entrypoint) {
+                return Err(LoadError::NoRegistry { path: path.into() });
+            }
+
+            let metadata = read_metadata(&library, path);
+            let module = crate::plugin::ffi::build_module(entrypoint)
+                .map_err(|message| plugin_error(path, message))?;
+
+            (module.into(), metadata, None)
+        } else {
+            let (module, metadata, context) =
+                self.build_module(&library, path, config, selection, &mut warnings)?;
+
+            (module, metadata, Some(context))
+        };
+
+        let library = rhai::Shared::new(library);
+        let module = keep_library_alive(module, &library, &mut warnings);
+
+        if let (Some(engine), Some(context)) = (engine, context) {
+            if let Ok(engine_entrypoint) =
+                unsafe { library.get::<EngineEntrypoint>(MODULE_ENGINE_ENTRYPOINT.as_bytes()) }
+            {
+                // Items registered in the engine point into the library, even if the entrypoint fails midway.
+                let mut guard = rhai::Module::new();
+                set_library_guard(&mut guard, &library);
+                engine.register_global_module(guard.into());
+
+                engine_entrypoint(engine, &context)
+                    .map_err(|message| plugin_error(path, message))?;
+            }
+        }
+
+        let handle = PluginHandle::new(self.next_handle);
+        self.next_handle += 1;
+
+        self.libraries.insert(
+            handle,
+            OpenLibrary {
+                library,
+                memory_file: None,
+                #[cfg(all(target_os = "linux", target_env = "gnu"))]
+                group: match &self.link_namespace {
+                    LinkNamespace::Group(group) => Some(group.clone()),
+                    _ => None,
+                },
+            },
+        );
+
+        Ok(LoadedPlugin {
+            handle,
+            module,
+            metadata,
+            warnings,
+        })
+    }
+}
+
+impl Loader for Libloading {
+    /// Load a rhai module from a dynamic library.
+    ///
+    /// The [`LoadError`] is returned as a [`rhai::EvalAltResult::ErrorInModule`] error.
+    fn load(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<LoadedPlugin, Box<rhai::EvalAltResult>> {
+        Ok(self.load_with_config(path, rhai::Map::new())?)
+    }
+}
+
+/// Make `module` keep `library` alive, so that its functions never outlive the code they point to.
+///
+/// Each native function of the module and of its sub-modules holds the library, so that functions
+/// copied to other modules keep it alive too. The module and each of its sub-modules hold it as well,
+/// for their variables and type iterators.
+///
+/// Functions are registered again, hashed by the host, see [`crate::plugin::hashing::rehash_module`].
+/// The items left out if the module has to be rebuilt are reported in `warnings`.
+fn keep_library_alive(
+    module: rhai::Shared<rhai::Module>,
+    library: &rhai::Shared<libloading::Library>,
+    warnings: &mut Vec<LoadWarning>,
+) -> rhai::Shared<rhai::Module> {
+    let (module, dropped) = map_functions(module, &|_, func| {
+        let guarded = LibraryFunction {
+            func: func.clone(),
+            _library: library.clone(),
+        };
+
+        match func {
+            RhaiFunc::Pure {
+                has_context,
+                is_pure,
+                is_volatile,
+                ..
+            } => Some(RhaiFunc::Pure {
+                func: rhai::Shared::new(move |context, args| guarded.call(context, args)),
+                has_context: *has_context,
+                is_pure: *is_pure,
+                is_volatile: *is_volatile,
+            }),
+            RhaiFunc::Method {
+                has_context,
+                is_pure,
+                is_volatile,
+                ..
+            } => Some(RhaiFunc::Method {
+                func: rhai::Shared::new(move |context, args| guarded.call(context, args)),
+                has_context: *has_context,
+                is_pure: *is_pure,
+                is_volatile: *is_volatile,
+            }),
+            RhaiFunc::Plugin { .. } => Some(RhaiFunc::Plugin {
+                func: rhai::Shared::new(guarded),
+            }),
+            _ => None,
+        }
+    });
+
+    if !dropped.is_empty() {
+        warnings.push(LoadWarning::DroppedItems(dropped));
+    }
+
+    set_module_guards(module, library)
+}
+
+/// Set a library guard on `module` and on each of its sub-modules, see [`set_library_guard`].
+fn set_module_guards(
+    module: rhai::Shared<rhai::Module>,
+    library: &rhai::Shared<libloading::Library>,
+) -> rhai::Shared<rhai::Module> {
+    let mut module = rhai::Shared::unwrap_or_clone(module);
+    set_library_guard(&mut module, library);
+
+    let sub_modules = module
+        .iter_sub_modules()
+        .map(|(name, sub_module)| (name.to_string(), sub_module.clone()))
+        .collect::<Vec<_>>();
+
+    for (name, sub_module) in sub_modules {
+        module.set_sub_module(name, set_module_guards(sub_module, library));
+    }
+
+    module.into()
+}
+
+/// A function of a library, holding the library so that it is not closed while the function exists.
+struct LibraryFunction {
+    /// Declared before the library, so that it is dropped first.
+    func: RhaiFunc,
+    _library: rhai::Shared<libloading::Library>,
+}
+
+impl LibraryFunction {
+    fn call(
+        &self,
+        context: Option<NativeCallContext>,
+        args: &mut [&mut rhai::Dynamic],
+    ) -> rhai::plugin::RhaiResult {
+        self.func
+            .get_native_fn()
+            .expect("only native functions are held with their library")(context, args)
+    }
+
+    fn plugin_fn(&self) -> &dyn PluginFunc {
+        &**self
+            .func
+            .get_plugin_fn()
+            .expect("only plugin functions are called as such")
+    }
+}
+
+impl PluginFunc for LibraryFunction {
+    fn call(
+        &self,
+        context: Option<NativeCallContext>,
+        args: &mut [&mut rhai::Dynamic],
+    ) -> rhai::plugin::RhaiResult {
+        self.plugin_fn().call(context, args)
+    }
+
+    fn is_method_call(&self) -> bool {
+        self.plugin_fn().is_method_call()
+    }
+
+    fn has_context(&self) -> bool {
+        self.plugin_fn().has_context()
+    }
+
+    fn is_pure(&self) -> bool {
+        self.plugin_fn().is_pure()
+    }
+
+    fn is_volatile(&self) -> bool {
+        self.plugin_fn().is_volatile()
+    }
+}
+
+/// Store a reference to `library` in `module`, so that the module, or the engine it is registered in,
+/// never outlives the code of the library.
+///
+/// The library is held by a type iterator that is never called.
+fn set_library_guard(module: &mut rhai::Module, library: &rhai::Shared<libloading::Library>) {
+    let library = library.clone();
+
+    module.set_iter(std::any::TypeId::of::<LibraryGuard>(), move |_| {
+        let _ = &library;
+        Box::new(std::iter::empty())
+    });
+}
+
+/// Read a record exported by a library through a function named `symbol`.
+fn read_record<T>(
+    library: &libloading::Library,
+    path: &std::path::Path,
+    symbol: &str,
+) -> Result<T, LoadError> {
+    let record = unsafe { library.get::<extern "C" fn() -> T>(symbol.as_bytes()) }
+        .map_err(|error| missing_symbol(path, symbol, error))?;
+
+    Ok(record())
+}
+
+/// Read the metadata record of a library.
+///
+/// Libraries without a record, such as plugins only exporting [`MODULE_ENTRYPOINT`], are named after their file stem.
+fn read_metadata(library: &libloading::Library, path: &std::path::Path) -> PluginMetadata {
+    let Ok(metadata) = (unsafe { library.get::<MetadataFn>(MODULE_METADATA.as_bytes()) }) else {
+        return PluginMetadata {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            ..PluginMetadata::default()
+        };
+    };
+
+    // Strings of the record point into the library, copy them before it can be unloaded.
+    (&metadata()).into()
+}
+
+/// Create an anonymous memory file named `name` holding `bytes`, sealed against any change.
+#[cfg(target_os = "linux")]
+fn memory_file(name: &str, bytes: &[u8]) -> std::io::Result<std::fs::File> {
+    use std::io::Write;
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    let name = std::ffi::CString::new(name)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error))?;
+
+    let fd =
+        unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    file.write_all(bytes)?;
+
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(file)
+}
+
+/// Number of partial shadow copies written by the process, used to name them.
+static PARTIAL_COPIES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// The default shadow copy directory, private to the current user.
+fn default_shadow_copy_directory() -> std::path::PathBuf {
+    #[cfg(unix)]
+    return std::env::temp_dir().join(format!("rhai-dylib-{}", unsafe { libc::geteuid() }));
+    #[cfg(not(unix))]
+    return std::env::temp_dir().join("rhai-dylib");
+}
+
+/// Create `directory` if it does not exist, then check that only the current user can write in it.
+///
+/// Libraries are opened from this directory: anyone able to write in it could replace them.
+fn create_private_directory(directory: &std::path::Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(directory)?;
+
+        // Symbolic links are not followed, a link is not a directory.
+        let metadata = std::fs::symlink_metadata(directory)?;
+
+        if !metadata.is_dir()
+            || metadata.uid() != unsafe { libc::geteuid() }
+            || metadata.mode() & 0o022 != 0
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} must be a directory owned by the current user, and only writable by them",
+                    directory.display()
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(directory)
+}
+
+/// Error of a library at `library_path` that could not be opened, unless the file does not exist.
+fn open_error(library_path: &std::path::Path, error: LoadError) -> LoadError {
+    match std::fs::metadata(library_path) {
+        // `dlopen` also searches system directories, the file is only checked once it failed.
+        Err(source) if source.kind() == std::io::ErrorKind::NotFound => LoadError::Io {
+            path: error.path().into(),
+            source,
+        },
+        _ => error,
+    }
+}
+
+/// Open the library at `path` in the link-map namespace `namespace`, returning the namespace it was opened in.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+unsafe fn dlmopen(
+    namespace: libc::Lmid_t,
+    path: &std::path::Path,
+    flags: std::os::raw::c_int,
+) -> Result<(libloading::Library, libc::Lmid_t), String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let filename =
+        std::ffi::CString::new(path.as_os_str().as_bytes()).map_err(|error| error.to_string())?;
+
+    let handle = libc::dlmopen(namespace, filename.as_ptr(), flags);
+
+    if handle.is_null() {
+        let error = libc::dlerror();
+
+        return Err(if error.is_null() {
+            "unknown `dlmopen` error".to_string()
+        } else {
+            std::ffi::CStr::from_ptr(error)
+                .to_string_lossy()
+                .into_owned()
+        });
+    }
+
+    let mut namespace = libc::LM_ID_BASE;
+    libc::dlinfo(handle, libc::RTLD_DI_LMID, (&raw mut namespace).cast());
+
+    Ok((
+        libloading::os::unix::Library::from_raw(handle).into(),
+        namespace,
+    ))
+}
+
+/// Error of a library at `path` using the Rust ABI, opened in its own namespace.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn rust_abi_in_namespace(path: &std::path::Path) -> LoadError {
+    LoadError::Dlmopen {
+        path: path.into(),
+        message: "only libraries using the C ABI can be opened in their own namespace".to_string(),
+    }
+}
+
+/// Does the ELF file `library` define the dynamic symbol `symbol`, read without opening the library.
+///
+/// Returns `None` if the symbols cannot be read, such as for a file that is not an ELF file
+/// of the class and byte order of the host.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn defines_dynamic_symbol(library: &[u8], symbol: &str) -> Option<bool> {
+    const SHT_DYNSYM: u64 = 11;
+
+    let read = |offset: usize, size: usize| -> Option<u64> {
+        let bytes = library.get(offset..offset.checked_add(size)?)?;
+        let mut value = [0; 8];
+        if cfg!(target_endian = "little") {
+            value[..size].copy_from_slice(bytes);
+            Some(u64::from_le_bytes(value))
+        } else {
+            value[8 - size..].copy_from_slice(bytes);
+            Some(u64::from_be_bytes(value))
+        }
+    };
+    let read_usize = |offset: usize, size: usize| usize::try_from(read(offset, size)?).ok();
+
+    // Offsets of the fields used in the file header, section headers and symbols, by class.
+    let is_64 = cfg!(target_pointer_width = "64");
+    let word = if is_64 { 8 } else { 4 };
+    let (section_headers, section_header_size, section_count) = if is_64 {
+        (0x28, 0x3a, 0x3c)
+    } else {
+        (0x20, 0x2e, 0x30)
+    };
+    let (section_type, section_offset, section_size, section_link, section_entry_size) = if is_64 {
+        (4, 24, 32, 40, 56)
+    } else {
+        (4, 16, 20, 24, 36)
+    };
+    let symbol_section = if is_64 { 6 } else { 14 };
+
+    let class = if is_64 { 2 } else { 1 };
+    let byte_order = if cfg!(target_endian = "little") { 1 } else { 2 };
+    if library.get(..6)? != [0x7f, b'E', b'L', b'F', class, byte_order] {
+        return None;
+    }
+
+    let section_headers = read_usize(section_headers, word)?;
+    let section_header_size = read_usize(section_header_size, 2)?;
+    let section =
+        |index: usize| section_headers.checked_add(index.checked_mul(section_header_size)?);
+
+    for index in 0..read_usize(section_count, 2)? {
+        let header = section(index)?;
+        if read(header + section_type, 4)? != SHT_DYNSYM {
+            continue;
+        }
+
+        let symbols = read_usize(header + section_offset, word)?;
+        let table_size = read_usize(header + section_size, word)?;
+        let entry_size = read_usize(header + section_entry_size, word)?;
+        let names = read_usize(
+            section(read_usize(header + section_link, 4)?)? + section_offset,
+            word,
+        )?;
+
+        if entry_size == 0 {
+            return None;
+        }
+
+        for entry in (symbols..symbols.checked_add(table_size)?).step_by(entry_size) {
+            let name = names.checked_add(read_usize(entry, 4)?)?;
+            let name = library.get(name..)?.split(|byte| *byte == 0).next()?;
+
+            // Undefined symbols have no section.
+            if name == symbol.as_bytes() && read(entry + symbol_section, 2)? != 0 {
+                return Some(true);
+            }
+        }
+
+        return Some(false);
+    }
+
+    None
+}
+
+/// Error of a library at `path` that does not export `symbol`.
+fn missing_symbol(path: &std::path::Path, symbol: &str, source: libloading::Error) -> LoadError {
+    LoadError::MissingSymbol {
+        path: path.into(),
+        symbol: symbol.to_string(),
+        source,
+    }
+}
+
+/// Error reported by the library at `path`.
+fn plugin_error(path: &std::path::Path, message: String) -> LoadError {
+    LoadError::Plugin {
+        path: path.into(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::Loader;
+
+    fn build_fixtures() -> &'static std::path::Path {
+        // Prevents multiple threads writing to the dll on windows and triggering a STATUS_ACCESS_VIOLATION error.
+        static PATH: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+        PATH.get_or_init(|| {
+            // The loader hands this seed to the test plugins.
+            let _ = rhai::config::hashing::set_hashing_seed(Some([1, 2, 3, 4]));
+
+            // The plugins are built with the features of the host, to pass the compatibility check.
+            let features = [
+                ("sync", cfg!(feature = "sync")),
+                ("metadata", cfg!(feature = "metadata")),
+            ]
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| *feature)
+            .collect::<Vec<_>>()
+            .join(",");
+
+            let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+            let status = std::process::Command::new("cargo")
+                .args([
+                    "build",
+                    "--example",
+                    "test_plugin",
+                    "--example",
+                    "fallible_plugin",
+                    "--example",
+                    "ffi_plugin",
+                    "--example",
+                    "registry_plugin",
+                    "--example",
+                    "legacy_plugin",
+                    "--features",
+                    &features,
+                ])
+                .current_dir(manifest_dir)
+                .status()
+                .expect("failed to execute cargo build");
+
+            assert!(status.success(), "building test plugins failed");
+
+            let target_dir = std::env::var("CARGO_TARGET_DIR")
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|_| manifest_dir.join("target"));
+
+            // Built like a third party would, in another workspace: the layouts of rhai types
+            // match the host's, but their type ids do not.
+            let rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+            let status = std::process::Command::new("cargo")
+                .args([
+                    "build",
+                    "--example",
+                    "mismatched_plugin",
+                    "--features",
+                    &features,
+                ])
+                .env("CARGO_TARGET_DIR", target_dir.join("third-party"))
+                .env("RUSTFLAGS", format!("{rustflags} -C metadata=third-party"))
+                .current_dir(manifest_dir)
+                .status()
+                .expect("failed to execute cargo build");
+
+            assert!(status.success(), "building the third party plugin failed");
+
+            target_dir.join("debug/examples")
+        })
+    }
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        #[cfg(target_os = "linux")]
+        return build_fixtures().join(format!("lib{name}.so"));
+        #[cfg(target_os = "macos")]
+        return build_fixtures().join(format!("lib{name}.dylib"));
+        #[cfg(target_os = "windows")]
+        return build_fixtures().join(format!("{name}.dll"));
+    }
+
+    fn third_party_fixture(name: &str) -> std::path::PathBuf {
+        let fixture = fixture(name);
+        let examples = fixture.parent().unwrap();
+        let target_dir = examples.parent().unwrap().parent().unwrap();
+
+        target_dir
+            .join("third-party/debug/examples")
+            .join(fixture.file_name().unwrap())
+    }
+
+    fn build_test_plugin() -> std::path::PathBuf {
+        fixture("test_plugin")
+    }
+
+    #[test]
+    fn new() {
+        let _ = Libloading::new();
+    }
+
+    #[test]
+    fn load_success() {
+        let mut loader = Libloading::new();
+        let plugin = loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+
+        assert_eq!(plugin.metadata.name, "test_plugin");
+        assert_eq!(plugin.metadata.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(plugin.metadata.description, "A plugin used by unit tests");
+        assert_eq!(plugin.metadata.authors, ["Lucas Tabis", "Stephen Chung"]);
+    }
+
+    #[test]
+    fn load_nonexistent_returns_error() {
+        let mut loader = Libloading::new();
+        let err = loader.load("nonexistent.so").unwrap_err();
+
+        let rhai::EvalAltResult::ErrorInModule(path, err, _) = *err else {
+            panic!("unexpected error: {err}");
+        };
+        let rhai::EvalAltResult::ErrorSystem(_, err) = *err else {
+            panic!("unexpected error: {err}");
+        };
+
+        assert_eq!(path, "nonexistent.so");
+        assert!(matches!(
+            err.downcast_ref::<LoadError>(),
+            Some(LoadError::Io { source, .. }) if source.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn load_invalid_library_returns_error() {
+        let directory = std::env::temp_dir().join("rhai-dylib-invalid-library");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(format!("corrupt.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&path, b"not a library").unwrap();
+
+        let err = Libloading::new()
+            .load_with_config(&path, rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(err, LoadError::Open { .. }));
+        assert_eq!(err.path(), path);
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn compatibility_check_toggle() {
+        let mut loader = Libloading::new();
+        assert!(loader.is_compatibility_check_enabled());
+
+        loader.enable_compatibility_check(false);
+        assert!(!loader.is_compatibility_check_enabled());
+    }
+
+    #[test]
+    fn hashing_seed_sharing_toggle() {
+        let mut loader = Libloading::new();
+        assert!(loader.is_hashing_seed_sharing_enabled());
+
+        loader.enable_hashing_seed_sharing(false);
+        assert!(!loader.is_hashing_seed_sharing_enabled());
+    }
+
+    #[test]
+    fn hashing_policy() {
+        let mut loader = Libloading::new();
+        assert_eq!(loader.hashing_policy(), HashingPolicy::Deny);
+
+        loader.set_hashing_policy(HashingPolicy::Ignore);
+        assert_eq!(loader.hashing_policy(), HashingPolicy::Ignore);
+    }
+
+    #[test]
+    fn type_id_policy() {
+        let mut loader = Libloading::new();
+        assert_eq!(loader.type_id_policy(), TypeIdPolicy::Deny);
+
+        loader.set_type_id_policy(TypeIdPolicy::Ignore);
+        loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+    }
+
+    #[test]
+    fn type_id_mismatch() {
+        let path = third_party_fixture("mismatched_plugin");
+        let mut loader = Libloading::new();
+        loader.set_hashing_policy(HashingPolicy::Ignore);
+
+        let err = loader
+            .load_library(&path, rhai::Map::new(), None, Selection::Entrypoint)
+            .unwrap_err();
+
+        // Types defined by rhai change with its build, types of the standard library do not.
+        let LoadError::TypeIds { source, .. } = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert!(source.types.contains(&"Map"));
+        assert!(!source.types.contains(&"INT"));
+
+        loader.set_type_id_policy(TypeIdPolicy::Warn);
+        let plugin = loader
+            .load(path.as_path())
+            .expect("failed to load mismatched_plugin");
+
+        // The module is also rebuilt, being hashed with the seed of the plugin.
+        assert_eq!(plugin.warnings[0], LoadWarning::TypeIds(source));
+
+        loader.set_type_id_policy(TypeIdPolicy::Ignore);
+        let plugin = loader
+            .load(path.as_path())
+            .expect("failed to load mismatched_plugin");
+
+        assert!(!plugin
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning, LoadWarning::TypeIds(_))));
+    }
+
+    #[test]
+    fn foreign_hashing_seed() {
+        let path = third_party_fixture("mismatched_plugin");
+        let mut loader = Libloading::new();
+        loader.set_type_id_policy(TypeIdPolicy::Ignore);
+
+        let err = loader
+            .load_with_config(&path, rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(
+            &err,
+            LoadError::Hashing { source, .. }
+                if source.host == Some([1, 2, 3, 4]) && source.plugin == Some([5, 6, 7, 8])
+        ));
+        assert!(err.to_string().contains("fingerprints"));
+
+        loader.set_hashing_policy(HashingPolicy::Warn);
+        let plugin = loader
+            .load(path.as_path())
+            .expect("failed to load mismatched_plugin");
+
+        assert!(
+            matches!(
+                plugin.warnings.as_slice(),
+                [
+                    LoadWarning::Hashing(HashingMismatch {
+                        host: Some([1, 2, 3, 4]),
+                        plugin: Some([5, 6, 7, 8]),
+                        ..
+                    }),
+                    LoadWarning::DroppedItems(dropped),
+                ] if dropped.type_iterators == 1 && dropped.private_functions == 0
+            ),
+            "unexpected warnings: {:?}",
+            plugin.warnings
+        );
+
+        // Private script-defined functions are carried over, with the library guard as type iterator.
+        assert_eq!(plugin.module.count(), (0, 2, 1));
+
+        // Custom types can only be listed with the metadata of an engine.
+        #[cfg(feature = "metadata")]
+        assert_eq!(
+            plugin
+                .module
+                .get_custom_type_display_by_name("mismatched_plugin::Answer"),
+            Some("Answer")
+        );
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("answer()").unwrap(), 42);
+    }
+
+    #[test]
+    fn shadow_copy() {
+        let directory =
+            std::env::temp_dir().join(format!("rhai-dylib-shadow-copy-{}", std::process::id()));
+        let mut loader = Libloading::new();
+        assert!(!loader.is_shadow_copy_enabled());
+
+        loader
+            .enable_shadow_copy(true)
+            .set_shadow_copy_directory(&directory);
+
+        loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+        loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin twice");
+
+        // Identical libraries share the same copy.
+        let copies = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(copies.len(), 1);
+
+        // A replaced copy is written again. The copy is mapped, it must not be modified in place.
+        std::fs::remove_file(&copies[0]).unwrap();
+        std::fs::write(&copies[0], b"not a library").unwrap();
+        loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin from a modified copy");
+        assert_eq!(
+            std::fs::read(&copies[0]).unwrap(),
+            std::fs::read(build_test_plugin()).unwrap()
+        );
+
+        // Loaders copying the same library at once do not write over each other's partial copies.
+        std::fs::remove_file(&copies[0]).unwrap();
+        let path = build_test_plugin();
+        let content = std::fs::read(&path).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let mut loader = Libloading::new();
+                    loader.set_shadow_copy_directory(&directory);
+
+                    for _ in 0..4 {
+                        let copy = loader
+                            .shadow_copy(&path, &content)
+                            .expect("failed to copy test_plugin");
+                        assert_eq!(copy, copies[0]);
+                    }
+                });
+            }
+        });
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shadow_copy_directory_writable_by_others() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = std::env::temp_dir().join(format!(
+            "rhai-dylib-shadow-copy-shared-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o777)).unwrap();
+
+        let mut loader = Libloading::new();
+        loader
+            .enable_shadow_copy(true)
+            .set_shadow_copy_directory(&directory);
+
+        assert!(matches!(
+            loader.load_with_config(build_test_plugin(), rhai::Map::new()),
+            Err(LoadError::Io { source, .. }) if source.kind() == std::io::ErrorKind::PermissionDenied
+        ));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn host_context() {
+        let mut loader = Libloading::new();
+
+        loader
+            .set_host_name("test_host")
+            .set_host_version("1.0.0")
+            .services_mut()
+            .register("answer", 42 as rhai::INT);
+
+        let plugin = loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<String>("host()").unwrap(), "test_host 1.0.0");
+        assert_eq!(engine.eval::<rhai::INT>("answer()").unwrap(), 42);
+    }
+
+    #[test]
+    fn load_with_config() {
+        let mut loader = Libloading::new();
+        let mut config = rhai::Map::new();
+        config.insert("answer".into(), (42 as rhai::INT).into());
+
+        let plugin = loader
+            .load_with_config(build_test_plugin().as_path(), config)
+            .expect("failed to load test_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("config().answer").unwrap(), 42);
+    }
+
+    #[test]
+    fn load_with_engine() {
+        let mut engine = rhai::Engine::new();
+
+        {
+            let mut loader = Libloading::new();
+            let plugin = loader
+                .load_with_engine(build_test_plugin().as_path(), rhai::Map::new(), &mut engine)
+                .expect("failed to load test_plugin");
+
+            engine.register_global_module(plugin.module);
+        }
+
+        assert_eq!(engine.eval::<rhai::INT>("1 plus 2").unwrap(), 3);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn failing_engine_entrypoint_keeps_library_alive() {
+        // A copy of its own, so that the library is not already resident from another test.
+        let library = std::env::temp_dir().join(format!(
+            "rhai-dylib-failing-engine-{}.so",
+            std::process::id()
+        ));
+        std::fs::copy(fixture("fallible_plugin"), &library).unwrap();
+
+        let mut engine = rhai::Engine::new();
+        let mut config = rhai::Map::new();
+        config.insert("greeting".into(), "Hello".into());
+        config.insert("fail_engine".into(), true.into());
+
+        {
+            let mut loader = Libloading::new();
+            loader.set_dlopen_options(*DlopenOptions::new().enable_no_delete(false));
+
+            let err = loader
+                .load_with_engine(&library, config, &mut engine)
+                .unwrap_err();
+
+            assert!(err.to_string().contains("configured to fail"));
+        }
+
+        // Registered before the entrypoint failed, the function still points into the library.
+        assert_eq!(engine.eval::<String>("registered()").unwrap(), "registered");
+
+        std::fs::remove_file(&library).unwrap();
+    }
+
+    #[test]
+    fn entrypoint_panic_returns_error() {
+        let mut loader = Libloading::new();
+        let mut config = rhai::Map::new();
+        config.insert("panic".into(), true.into());
+
+        let err = loader
+            .load_with_config(build_test_plugin().as_path(), config)
+            .unwrap_err();
+
+        assert!(matches!(err, LoadError::Plugin { .. }));
+        assert!(err
+            .to_string()
+            .contains("plugin panicked: configured to panic"));
+    }
+
+    #[test]
+    fn fallible_entrypoint() {
+        let mut loader = Libloading::new();
+        let mut config = rhai::Map::new();
+        config.insert("greeting".into(), "Hello".into());
+
+        let plugin = loader
+            .load_with_config(fixture("fallible_plugin").as_path(), config)
+            .expect("failed to load fallible_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(
+            engine.eval::<String>(r#"greet("World")"#).unwrap(),
+            "Hello World"
+        );
+    }
+
+    #[test]
+    fn fallible_entrypoint_returns_error() {
+        let mut loader = Libloading::new();
+        let err = loader
+            .load(fixture("fallible_plugin").as_path())
+            .unwrap_err();
+
+        assert!(matches!(*err, rhai::EvalAltResult::ErrorInModule(..)));
+        assert!(err.to_string().contains("missing `greeting` configuration"));
+    }
+
+    #[test]
+    fn renamed_entrypoint() {
+        let mut loader = Libloading::new();
+        loader.set_entrypoint_symbols([
+            EntrypointSymbol::EntrypointV2("missing_entrypoint".into()),
+            EntrypointSymbol::Entrypoint("greeter_entrypoint".into()),
+        ]);
+
+        let plugin = loader
+            .load(fixture("fallible_plugin").as_path())
+            .expect("failed to load fallible_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(
+            engine.eval::<String>(r#"greet("World")"#).unwrap(),
+            "Hi World"
+        );
+
+        loader.set_entrypoint_symbols([EntrypointSymbol::Entrypoint("missing_entrypoint".into())]);
+
+        let err = loader
+            .load(fixture("fallible_plugin").as_path())
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("failed to find `missing_entrypoint` or `module_names`"));
+    }
+
+    #[test]
+    fn load_ffi_plugin() {
+        let mut loader = Libloading::new();
+        let plugin = loader
+            .load(fixture("ffi_plugin").as_path())
+            .expect("failed to load ffi_plugin");
+
+        assert_eq!(plugin.metadata.name, "ffi_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("multiply(6, 7)").unwrap(), 42);
+        assert_eq!(engine.eval::<String>(r#"shout("hello")"#).unwrap(), "HELLO");
+
+        let err = engine.eval::<rhai::INT>("multiply(6, true)").unwrap_err();
+
+        assert!(matches!(*err, rhai::EvalAltResult::ErrorRuntime(..)));
+        assert!(err.to_string().contains("expected two integers"));
+    }
+
+    #[test]
+    fn load_module_by_name() {
+        let mut loader = Libloading::new();
+        let plugin = loader
+            .load_module(fixture("registry_plugin"), "math", rhai::Map::new())
+            .expect("failed to load the `math` module");
+
+        assert_eq!(plugin.metadata.name, "registry_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("square(7)").unwrap(), 49);
+
+        let err = loader
+            .load_module(fixture("registry_plugin"), "json", rhai::Map::new())
+            .unwrap_err();
+
+        assert!(err.to_string().contains("no module named `json`"));
+    }
+
+    #[test]
+    fn load_all_modules() {
+        let mut loader = Libloading::new();
+        let plugins = loader
+            .load_modules(fixture("registry_plugin"), rhai::Map::new())
+            .expect("failed to load registry_plugin");
+
+        assert_eq!(
+            plugins.keys().collect::<Vec<_>>(),
+            vec!["constants", "math", "text"]
+        );
+        assert_eq!(plugins["math"].handle, plugins["text"].handle);
+
+        let mut config = rhai::Map::new();
+        config.insert("no_text".into(), true.into());
+
+        let err = loader
+            .load_modules(fixture("registry_plugin"), config)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("text module disabled"));
+
+        // Without an entrypoint, the modules are sub-modules of the module of the library.
+        let plugin = loader
+            .load(fixture("registry_plugin"))
+            .expect("failed to load registry_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_static_module("stdlib", plugin.module);
+
+        assert_eq!(
+            engine
+                .eval::<String>(r#"stdlib::text::shout("hi") + stdlib::math::square(2)"#)
+                .unwrap(),
+            "HI4"
+        );
+    }
+
+    #[test]
+    fn function_panic_returns_error() {
+        let mut loader = Libloading::new();
+        loader.enable_panic_catching("test_plugin", true);
+
+        assert!(loader.is_panic_catching_enabled("test_plugin"));
+        assert!(!loader.is_panic_catching_enabled("other_plugin"));
+
+        let plugin = loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("add(1, 2)").unwrap(), 3);
+
+        let err = engine.eval::<rhai::INT>("fail()").unwrap_err();
+
+        assert!(matches!(*err, rhai::EvalAltResult::ErrorRuntime(..)));
+        assert!(err
+            .to_string()
+            .contains("plugin `test_plugin` panicked in function `fail`: function panicked"));
+    }
+
+    #[test]
+    fn unload() {
+        let mut loader = Libloading::new();
+        let plugin = loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+
+        assert!(matches!(
+            loader.unload(plugin.handle),
+            Err(UnloadError::InUse { .. })
+        ));
+
+        let handle = plugin.handle;
+        drop(plugin);
+
+        loader.unload(handle).expect("failed to unload test_plugin");
+
+        assert!(matches!(
+            loader.unload(handle),
+            Err(UnloadError::UnknownHandle(_))
+        ));
+    }
+
+    #[test]
+    fn module_outlives_loader() {
+        let module = {
+            let mut loader = Libloading::new();
+            loader
+                .load(build_test_plugin().as_path())
+                .expect("failed to load test_plugin")
+                .module
+        };
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(module);
+
+        assert_eq!(engine.eval::<rhai::INT>("add(1, 2)").unwrap(), 3);
+    }
+
+    #[test]
+    fn functions_keep_library_alive() {
+        let mut loader = Libloading::new();
+        let plugin = loader
+            .load(build_test_plugin().as_path())
+            .expect("failed to load test_plugin");
+        let registry = loader
+            .load(fixture("registry_plugin").as_path())
+            .expect("failed to load registry_plugin");
+
+        // Both modules used to hold their library under the same key, the last one won.
+        let mut combined = rhai::Module::new();
+        combined.combine(rhai::Shared::unwrap_or_clone(plugin.module));
+        combined.combine(rhai::Shared::unwrap_or_clone(registry.module));
+
+        assert!(matches!(
+            loader.unload(plugin.handle),
+            Err(UnloadError::InUse { .. })
+        ));
+
+        drop(combined);
+
+        loader
+            .unload(plugin.handle)
+            .expect("failed to unload test_plugin");
+        loader
+            .unload(registry.handle)
+            .expect("failed to unload registry_plugin");
+    }
+
+    #[test]
+    fn modules_keep_library_alive() {
+        let mut loader = Libloading::new();
+
+        // A module without functions.
+        let plugin = loader
+            .load_module(fixture("registry_plugin"), "constants", rhai::Map::new())
+            .expect("failed to load the `constants` module");
+
+        assert!(matches!(
+            loader.unload(plugin.handle),
+            Err(UnloadError::InUse { .. })
+        ));
+
+        let mut engine = rhai::Engine::new();
+        engine.register_static_module("constants", plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("constants::answer").unwrap(), 42);
+
+        drop(engine);
+        loader
+            .unload(plugin.handle)
+            .expect("failed to unload registry_plugin");
+
+        // A sub-module outliving the module of its library.
+        let mut plugins = loader
+            .load_modules(fixture("registry_plugin"), rhai::Map::new())
+            .expect("failed to load registry_plugin");
+        let constants = plugins.remove("constants").unwrap();
+        drop(plugins);
+
+        assert!(matches!(
+            loader.unload(constants.handle),
+            Err(UnloadError::InUse { .. })
+        ));
+
+        drop(constants.module);
+        loader
+            .unload(constants.handle)
+            .expect("failed to unload registry_plugin");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn dlopen_options() {
+        assert_eq!(
+            DlopenOptions::default().flags(),
+            libc::RTLD_NOW | libc::RTLD_LOCAL | libc::RTLD_NODELETE
+        );
+
+        let modes = [
+            DlopenOptions::new(),
+            *DlopenOptions::new().set_binding(SymbolBinding::Lazy),
+            *DlopenOptions::new().set_visibility(SymbolVisibility::Global),
+            *DlopenOptions::new().enable_no_delete(false),
+            *DlopenOptions::new().enable_deep_bind(true),
+        ];
+
+        for (index, options) in modes.into_iter().enumerate() {
+            // A copy of its own for each mode, so that the library is not already resident with other flags.
+            let library = std::env::temp_dir().join(format!(
+                "rhai-dylib-dlopen-options-{}-{index}.so",
+                std::process::id()
+            ));
+            std::fs::copy(build_test_plugin(), &library).unwrap();
+
+            let mut loader = Libloading::new();
+            loader.set_dlopen_options(options);
+
+            let plugin = loader.load(&library).unwrap_or_else(|error| {
+                panic!("failed to load test_plugin with {options:?}: {error}")
+            });
+            let handle = plugin.handle;
+
+            let mut engine = rhai::Engine::new();
+            engine.register_global_module(plugin.module);
+
+            assert_eq!(engine.eval::<rhai::INT>("add(1, 2)").unwrap(), 3);
+
+            // Symbols of libraries opened with `RTLD_GLOBAL` are visible to the host.
+            if options.visibility() == SymbolVisibility::Global {
+                let symbol =
+                    unsafe { libc::dlsym(libc::RTLD_DEFAULT, c"module_metadata".as_ptr()) };
+                let mut info = unsafe { std::mem::zeroed::<libc::Dl_info>() };
+
+                assert_ne!(unsafe { libc::dladdr(symbol, &raw mut info) }, 0);
+                assert_eq!(
+                    unsafe { std::ffi::CStr::from_ptr(info.dli_fname) }
+                        .to_str()
+                        .ok(),
+                    library.to_str()
+                );
+            }
+
+            drop(engine);
+            loader.unload(handle).unwrap_or_else(|error| {
+                panic!("failed to unload test_plugin with {options:?}: {error}")
+            });
+
+            // Without `RTLD_NODELETE`, the library is really unloaded.
+            let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+            assert_eq!(
+                maps.contains(library.to_str().unwrap()),
+                options.is_no_delete_enabled(),
+                "unexpected mapping of test_plugin with {options:?}"
+            );
+
+            std::fs::remove_file(&library).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "signature")]
+    fn signature_verification() {
+        use crate::loader::signature::{detached_signature_path, SignatureError};
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let library = std::fs::read(build_test_plugin()).expect("failed to read test_plugin");
+
+        let mut loader = Libloading::new();
+        loader
+            .enable_signature_verification(true)
+            .add_trusted_key(key.verifying_key());
+
+        let err = loader
+            .load_with_config(build_test_plugin().as_path(), rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            LoadError::Signature {
+                source: SignatureError::Unsigned,
+                ..
+            }
+        ));
+
+        let directory = std::env::temp_dir().join("rhai-dylib-signed-plugin");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory
+            .join(format!("signed-{}", std::process::id()))
+            .with_extension(build_test_plugin().extension().unwrap());
+        std::fs::write(&path, &library).unwrap();
+        std::fs::write(
+            detached_signature_path(&path),
+            SigningKey::from_bytes(&[8; 32]).sign(&library).to_bytes(),
+        )
+        .unwrap();
+
+        let err = loader
+            .load_with_config(&path, rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            LoadError::Signature {
+                source: SignatureError::Untrusted,
+                ..
+            }
+        ));
+
+        std::fs::write(
+            detached_signature_path(&path),
+            key.sign(&library).to_bytes(),
+        )
+        .unwrap();
+
+        let plugin = loader
+            .load(&path)
+            .expect("failed to load signed test_plugin");
+
+        assert_eq!(plugin.metadata.name, "test_plugin");
+
+        #[cfg(target_os = "linux")]
+        {
+            // The library is opened from the bytes that were verified, not from the file.
+            let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+            let name = path.file_name().unwrap().to_string_lossy();
+            assert!(maps.contains(&format!("memfd:{name}")));
+
+            let signed = crate::loader::signature::embed_signature(&library, &key.sign(&library));
+            let plugin = loader
+                .load_from_bytes("test_plugin", &signed)
+                .expect("failed to load test_plugin with an embedded signature");
+
+            assert_eq!(plugin.metadata.name, "test_plugin");
+            assert!(matches!(
+                loader.load_from_bytes("test_plugin", &library),
+                Err(LoadError::Signature {
+                    source: SignatureError::Unsigned,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "pinning")]
+    fn digest_pinning() {
+        use crate::loader::pinning::{digest, DigestPins};
+
+        let library = std::fs::read(build_test_plugin()).expect("failed to read test_plugin");
+        let directory = std::env::temp_dir().join("rhai-dylib-pinned-plugin");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory
+            .join(format!("pinned-{}", std::process::id()))
+            .with_extension(build_test_plugin().extension().unwrap());
+        std::fs::write(&path, &library).unwrap();
+
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let mut loader = Libloading::new();
+
+        loader.set_digest_pins(Some(
+            DigestPins::new()
+                .pin(name.clone(), digest(b"another build"))
+                .clone(),
+        ));
+
+        assert!(matches!(
+            loader.load_with_config(&path, rhai::Map::new()),
+            Err(LoadError::Pinning { .. })
+        ));
+
+        loader.set_digest_pins(Some(
+            DigestPins::new()
+                .pin(name.clone(), digest(&library))
+                .clone(),
+        ));
+
+        let plugin = loader
+            .load_with_config(&path, rhai::Map::new())
+            .expect("failed to load pinned test_plugin");
+
+        assert_eq!(plugin.metadata.name, "test_plugin");
+
+        // The library is opened from the bytes that were checked, not from the file.
+        #[cfg(target_os = "linux")]
+        assert!(std::fs::read_to_string("/proc/self/maps")
+            .unwrap()
+            .contains(&format!("memfd:{name}")));
+
+        // Unloading closes the memory file, reloading opens a new one.
+        let mut handle = plugin.handle;
+        drop(plugin);
+
+        for _ in 0..3 {
+            loader.unload(handle).unwrap();
+            #[cfg(target_os = "linux")]
+            assert_eq!(memory_files(&name), 0);
+
+            let plugin = loader
+                .load_with_config(&path, rhai::Map::new())
+                .expect("failed to reload pinned test_plugin");
+            #[cfg(target_os = "linux")]
+            assert_eq!(memory_files(&name), 1);
+
+            let mut engine = rhai::Engine::new();
+            engine.register_global_module(plugin.module);
+            assert_eq!(engine.eval::<rhai::INT>("add(1, 2)").unwrap(), 3);
+
+            handle = plugin.handle;
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Number of memory files named `name` open in the process.
+    #[cfg(target_os = "linux")]
+    fn memory_files(name: &str) -> usize {
+        std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+            .filter(|target| {
+                target
+                    .to_string_lossy()
+                    .starts_with(&format!("/memfd:{name}"))
+            })
+            .count()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn load_from_bytes() {
+        let bytes = std::fs::read(build_test_plugin()).expect("failed to read test_plugin");
+        let name = format!("rhai-dylib-from-bytes-{}", std::process::id());
+
+        let mut loader = Libloading::new();
+        let plugin = loader
+            .load_from_bytes(&name, &bytes)
+            .expect("failed to load test_plugin from memory");
+
+        assert_eq!(plugin.metadata.name, "test_plugin");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("add(1, 2)").unwrap(), 3);
+        assert_eq!(memory_files(&name), 1);
+
+        // The memory file is closed with the library, which stays loaded with `RTLD_NODELETE`.
+        drop(engine);
+        loader.unload(plugin.handle).unwrap();
+
+        assert_eq!(memory_files(&name), 0);
+
+        let err = loader
+            .load_from_bytes("corrupt", b"not a library")
+            .unwrap_err();
+
+        assert!(matches!(err, LoadError::Open { .. }));
+        assert_eq!(err.path(), std::path::Path::new("corrupt"));
+
+        // Each library gets its own memory file, never mistaken for a previous one.
+        let bytes = std::fs::read(fixture("ffi_plugin")).expect("failed to read ffi_plugin");
+        let plugin = loader
+            .load_from_bytes("ffi_plugin", &bytes)
+            .expect("failed to load ffi_plugin from memory");
+
+        assert_eq!(plugin.metadata.name, "ffi_plugin");
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[test]
+    fn link_namespaces() {
+        let mut loader = Libloading::new();
+        loader.set_link_namespace(LinkNamespace::Isolated);
+
+        let isolated = loader
+            .load(fixture("ffi_plugin").as_path())
+            .expect("failed to load ffi_plugin in its own namespace");
+
+        loader.set_link_namespace(LinkNamespace::Group("plugins".into()));
+
+        let first = loader
+            .load(fixture("ffi_plugin").as_path())
+            .expect("failed to load ffi_plugin in a group");
+        let second = loader
+            .load(fixture("ffi_plugin").as_path())
+            .expect("failed to load ffi_plugin in a group");
+
+        assert_eq!(loader.namespaces.len(), 1);
+        assert_ne!(loader.namespaces["plugins"], libc::LM_ID_BASE);
+
+        let mut engine = rhai::Engine::new();
+        engine
+            .register_static_module("isolated", isolated.module)
+            .register_static_module("first", first.module)
+            .register_static_module("second", second.module);
+
+        assert_eq!(
+            engine
+                .eval::<String>(
+                    r#"isolated::shout("a") + first::shout("b") + second::multiply(2, 3)"#
+                )
+                .unwrap(),
+            "AB6"
+        );
+
+        // Namespaces of groups are forgotten with their last library.
+        drop(engine);
+        loader.unload(first.handle).unwrap();
+        assert!(loader.namespaces.contains_key("plugins"));
+        loader.unload(second.handle).unwrap();
+        assert!(loader.namespaces.is_empty());
+
+        // A copy of its own, so that the library is not already resident from another test.
+        let library = std::env::temp_dir().join(format!(
+            "rhai-dylib-rust-abi-namespace-{}.so",
+            std::process::id()
+        ));
+        std::fs::copy(build_test_plugin(), &library).unwrap();
+
+        let err = loader
+            .load_with_config(&library, rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(err, LoadError::Dlmopen { .. }));
+        assert!(err.to_string().contains("C ABI"));
+
+        // The library is refused before being opened.
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        assert!(!maps.contains(library.to_str().unwrap()));
+
+        std::fs::remove_file(&library).unwrap();
+
+        let err = loader
+            .load_with_config("nonexistent.so", rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(err, LoadError::Io { .. }));
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[test]
+    fn dynamic_symbols() {
+        let symbols = |name: &str| {
+            let library = std::fs::read(fixture(name)).unwrap();
+            defines_dynamic_symbol(&library, MODULE_FFI_ENTRYPOINT)
+        };
+
+        assert_eq!(symbols("ffi_plugin"), Some(true));
+        assert_eq!(symbols("test_plugin"), Some(false));
+        assert_eq!(
+            defines_dynamic_symbol(b"not a library", MODULE_FFI_ENTRYPOINT),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn load_without_compatibility_record_returns_error() {
+        let mut loader = Libloading::new();
+        let err = loader.load("libc.so.6").unwrap_err();
+
+        assert!(err.to_string().contains(MODULE_COMPATIBILITY));
+
+        let err = loader
+            .load_with_config("libc.so.6", rhai::Map::new())
+            .unwrap_err();
+
+        assert!(
+            matches!(err, LoadError::MissingSymbol { ref symbol, .. } if symbol == MODULE_COMPATIBILITY)
+        );
+
+        loader
+            .enable_compatibility_check(false)
+            .set_type_id_policy(TypeIdPolicy::Ignore);
+        let err = loader
+            .load_with_config("libc.so.6", rhai::Map::new())
+            .unwrap_err();
+
+        assert!(matches!(err, LoadError::NoEntrypoint { .. }));
+    }
+
+    #[test]
+    fn load_legacy_plugin() {
+        let path = fixture("legacy_plugin");
+        let mut loader = Libloading::new();
+
+        assert!(loader.load(path.as_path()).is_err());
+
+        loader
+            .enable_compatibility_check(false)
+            .set_type_id_policy(TypeIdPolicy::Ignore)
+            .set_hashing_policy(HashingPolicy::Ignore);
+
+        let plugin = loader
+            .load(path.as_path())
+            .expect("failed to load legacy_plugin");
+
+        assert_eq!(
+            plugin.metadata,
+            PluginMetadata {
+                name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                ..PluginMetadata::default()
+            }
+        );
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(plugin.module);
+
+        assert_eq!(engine.eval::<rhai::INT>("answer()").unwrap(), 42);
+    }
+
+    #[test]
+    fn declared_symbols() {
+        use crate::plugin::{self, ffi::FfiEntrypoint};
+
+        // Resolve a symbol with the prototype the loader expects, `None` if it is not exported.
+        fn resolve<T: Copy>(library: &libloading::Library, name: &str) -> Option<T> {
+            unsafe { library.get::<T>(name.as_bytes()) }
+                .ok()
+                .map(|symbol| *symbol)
+        }
+
+        for (name, registry) in [("test_plugin", false), ("registry_plugin", true)] {
+            let library = unsafe { libloading::Library::new(fixture(name)) }
+                .unwrap_or_else(|error| panic!("failed to open {name}: {error}"));
+
+            let metadata = resolve::<plugin::MetadataFn>(&library, plugin::MODULE_METADATA)
+                .unwrap_or_else(|| panic!("{name} does not export its metadata"));
+            assert_eq!(metadata().name(), name);
+
+            let compatibility =
+                resolve::<plugin::CompatibilityFn>(&library, plugin::MODULE_COMPATIBILITY)
+                    .unwrap_or_else(|| panic!("{name} does not export its compatibility record"));
+            assert!(!compatibility().is_null());
+
+            let type_ids = resolve::<plugin::TypeIdsFn>(&library, plugin::MODULE_TYPE_IDS)
+                .unwrap_or_else(|| panic!("{name} does not export its type ids"));
+            assert!(type_ids().mismatches(&TypeIds::current()).is_empty());
+
+            assert!(resolve::<plugin::HashingFn>(&library, plugin::MODULE_HASHING).is_some());
+            assert!(
+                resolve::<plugin::SetHashingSeedFn>(&library, plugin::MODULE_SET_HASHING_SEED)
+                    .is_some()
+            );
+
+            assert_eq!(
+                resolve::<plugin::EntrypointV2>(&library, plugin::MODULE_ENTRYPOINT_V2).is_some(),
+                !registry
+            );
+            assert_eq!(
+                resolve::<plugin::EngineEntrypoint>(&library, plugin::MODULE_ENGINE_ENTRYPOINT)
+                    .is_some(),
+                !registry
+            );
+            assert_eq!(
+                resolve::<plugin::ModuleNamesFn>(&library, plugin::MODULE_NAMES).is_some(),
+                registry
+            );
+            assert_eq!(
+                resolve::<plugin::NamedEntrypoint>(&library, plugin::MODULE_NAMED_ENTRYPOINT)
+                    .is_some(),
+                registry
+            );
+
+            // Only plugins written by hand export these.
+            assert!(resolve::<plugin::Entrypoint>(&library, plugin::MODULE_ENTRYPOINT).is_none());
+            assert!(resolve::<FfiEntrypoint>(&library, plugin::MODULE_FFI_ENTRYPOINT).is_none());
+        }
     }
 }
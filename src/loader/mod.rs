@@ -2,16 +2,104 @@
 //!
 //! A [`Loader`] is a simple trait that will be used to load a rhai module from a path.
 
+use crate::plugin::hashing::{DroppedItems, HashingMismatch};
+use crate::plugin::metadata::Metadata;
+use crate::plugin::type_ids::TypeIdMismatch;
+
 /// A loader using the [`libloading`](https://github.com/nagisa/rust_libloading) crate.
 #[cfg(feature = "libloading")]
 pub mod libloading;
+/// Checking the digests of libraries before loading them.
+#[cfg(feature = "pinning")]
+pub mod pinning;
+/// Verifying the signatures of libraries before loading them.
+#[cfg(feature = "signature")]
+pub mod signature;
 
 /// A trait to implement an object that loads Rhai modules.
 pub trait Loader {
-    /// Load a module from a path and apply it to a [`rhai::Engine`].
+    /// Load a module from a path.
     #[allow(clippy::missing_errors_doc)]
     fn load(
         &mut self,
         path: impl AsRef<std::path::Path>,
-    ) -> Result<rhai::Shared<rhai::Module>, Box<rhai::EvalAltResult>>;
+    ) -> Result<LoadedPlugin, Box<rhai::EvalAltResult>>;
+}
+
+/// Identifies a library loaded by a [`Loader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PluginHandle(u64);
+
+impl PluginHandle {
+    /// Create a handle from its raw value.
+    #[must_use]
+    pub const fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    /// The raw value of the handle.
+    #[must_use]
+    pub const fn value(self) -> u64 {
+        self.0
+    }
+}
+
+/// A plugin loaded by a [`Loader`].
+#[derive(Debug, Clone)]
+pub struct LoadedPlugin {
+    /// Handle of the library the plugin was loaded from.
+    pub handle: PluginHandle,
+    /// The module built by the plugin.
+    pub module: rhai::Shared<rhai::Module>,
+    /// The description of the plugin.
+    pub metadata: PluginMetadata,
+    /// Problems found while loading the plugin, which did not prevent it from loading.
+    pub warnings: Vec<LoadWarning>,
+}
+
+/// A problem found while loading a plugin, which did not prevent it from loading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadWarning {
+    /// The type ids of core Rhai types differ between the plugin and the host,
+    /// see [`TypeIdPolicy::Warn`](libloading::TypeIdPolicy::Warn).
+    TypeIds(TypeIdMismatch),
+    /// The plugin does not hash functions like the host, see [`HashingPolicy::Warn`](libloading::HashingPolicy::Warn).
+    /// Its module is hashed again by the host, but the plugin can fail to call functions through Rhai itself.
+    Hashing(HashingMismatch),
+    /// The module of the plugin was rebuilt to hash its functions like the host, and lost some items.
+    DroppedItems(DroppedItems),
+}
+
+impl std::fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TypeIds(mismatch) => write!(f, "{mismatch}"),
+            Self::Hashing(mismatch) => write!(f, "{mismatch}"),
+            Self::DroppedItems(dropped) => write!(f, "{dropped}"),
+        }
+    }
+}
+
+/// Description of a loaded plugin, copied from its [`Metadata`] record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginMetadata {
+    /// Name of the plugin.
+    pub name: String,
+    /// Version of the plugin.
+    pub version: String,
+    /// Description of the plugin.
+    pub description: String,
+    /// Authors of the plugin.
+    pub authors: Vec<String>,
+}
+
+impl From<&Metadata> for PluginMetadata {
+    fn from(metadata: &Metadata) -> Self {
+        Self {
+            name: metadata.name().into_owned(),
+            version: metadata.version().into_owned(),
+            description: metadata.description().into_owned(),
+            authors: metadata.authors(),
+        }
+    }
 }
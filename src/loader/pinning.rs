@@ -0,0 +1,225 @@
+//! # Digest pinning.
+//!
+//! The host lists the SHA-256 digests of the libraries it accepts in [`DigestPins`], see
+//! [`Libloading::set_digest_pins`](super::libloading::Libloading::set_digest_pins). A library whose
+//! content does not match is refused before it is opened, so that a library swapped on disk is never run.
+//! The library is then opened from the bytes that were checked, so that it cannot be swapped after the check either.
+//!
+//! Digests are either allowed for any library, or pinned to the file name of a library by a lockfile
+//! in the format of `sha256sum`:
+//!
+//! ```text
+//! # plugins.lock
+//! 5f70bf18a086007016e948b04aed3b82103a36bea41755b6cddfaf10ace3c6ef  libmy_plugin.so
+//! ```
+
+use sha2::Digest as _;
+
+/// SHA-256 digest of a library.
+pub type Digest = [u8; 32];
+
+/// Compute the digest of the content of a library.
+#[must_use]
+pub fn digest(library: &[u8]) -> Digest {
+    sha2::Sha256::digest(library).into()
+}
+
+/// Parse a digest written in hexadecimal.
+#[must_use]
+pub fn parse_digest(hex: &str) -> Option<Digest> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut digest = Digest::default();
+
+    for (byte, pair) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+
+    Some(digest)
+}
+
+/// Write a digest in hexadecimal.
+#[must_use]
+pub fn format_digest(digest: &Digest) -> String {
+    use std::fmt::Write;
+
+    digest.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Digests of the libraries a host accepts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DigestPins {
+    /// Digests accepted for libraries whose name is not pinned.
+    allowed: std::collections::BTreeSet<Digest>,
+    /// Digests pinned by library file name.
+    pinned: std::collections::BTreeMap<String, Digest>,
+}
+
+impl DigestPins {
+    /// Create an empty set of pins, refusing every library.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read pins from a lockfile in the format of `sha256sum`, one digest and one library file name per line.
+    ///
+    /// Empty lines and lines starting with `#` are ignored. Paths are reduced to their file name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a line does not hold a digest followed by a file name.
+    pub fn from_lockfile(lockfile: &str) -> Result<Self, LockfileError> {
+        let mut pins = Self::new();
+
+        for (index, line) in lockfile.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || LockfileError { line: index + 1 };
+
+            let (digest, path) = line.split_once(char::is_whitespace).ok_or_else(error)?;
+            let digest = parse_digest(digest).ok_or_else(error)?;
+            // `sha256sum` marks files read in binary mode with `*`.
+            let path = std::path::Path::new(path.trim_start().trim_start_matches('*'));
+            let name = path.file_name().ok_or_else(error)?;
+
+            pins.pin(name.to_string_lossy(), digest);
+        }
+
+        Ok(pins)
+    }
+
+    /// Accept any library with this digest, unless its name is pinned to another digest.
+    pub fn allow(&mut self, digest: Digest) -> &mut Self {
+        self.allowed.insert(digest);
+        self
+    }
+
+    /// Only accept the library with the file name `name` if it has this digest.
+    pub fn pin(&mut self, name: impl Into<String>, digest: Digest) -> &mut Self {
+        self.pinned.insert(name.into(), digest);
+        self
+    }
+
+    /// Check the content of the library with the file name `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name of the library is pinned to another digest,
+    /// or if it is not pinned and its digest is not allowed.
+    pub fn check(&self, name: &str, library: &[u8]) -> Result<(), PinError> {
+        let actual = digest(library);
+
+        match self.pinned.get(name) {
+            Some(expected) if *expected == actual => Ok(()),
+            Some(expected) => Err(PinError::Mismatch {
+                expected: *expected,
+                actual,
+            }),
+            None if self.allowed.contains(&actual) => Ok(()),
+            None => Err(PinError::NotAllowed { actual }),
+        }
+    }
+}
+
+/// Error returned when a lockfile cannot be read, see [`DigestPins::from_lockfile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockfileError {
+    /// Number of the invalid line, starting at 1.
+    pub line: usize,
+}
+
+impl std::fmt::Display for LockfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid lockfile line {}, expected a SHA-256 digest and a file name",
+            self.line
+        )
+    }
+}
+
+impl std::error::Error for LockfileError {}
+
+/// Error returned when a library does not match the digests accepted by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinError {
+    /// The name of the library is pinned to another digest.
+    Mismatch {
+        /// The pinned digest.
+        expected: Digest,
+        /// The digest of the library.
+        actual: Digest,
+    },
+    /// The name of the library is not pinned, and its digest is not allowed.
+    NotAllowed {
+        /// The digest of the library.
+        actual: Digest,
+    },
+}
+
+impl std::fmt::Display for PinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch { expected, actual } => write!(
+                f,
+                "library digest mismatch (expected: {}, actual: {})",
+                format_digest(expected),
+                format_digest(actual)
+            ),
+            Self::NotAllowed { actual } => {
+                write!(f, "library digest {} is not allowed", format_digest(actual))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PinError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins() {
+        let library = b"\x7fELF not really a library";
+        let hex = format_digest(&digest(library));
+
+        assert_eq!(parse_digest(&hex), Some(digest(library)));
+        assert_eq!(parse_digest("not a digest"), None);
+
+        let pins = DigestPins::from_lockfile(&format!(
+            "# plugins\n\n{hex}  plugins/libfirst.so\n{hex} *libsecond.so\n"
+        ))
+        .unwrap();
+
+        assert!(pins.check("libfirst.so", library).is_ok());
+        assert!(pins.check("libsecond.so", library).is_ok());
+        assert!(matches!(
+            pins.check("libfirst.so", b"swapped"),
+            Err(PinError::Mismatch { .. })
+        ));
+        assert!(matches!(
+            pins.check("libthird.so", library),
+            Err(PinError::NotAllowed { .. })
+        ));
+
+        let mut pins = pins;
+        pins.allow(digest(library));
+
+        assert!(pins.check("libthird.so", library).is_ok());
+        assert_eq!(
+            DigestPins::from_lockfile("libfirst.so").unwrap_err(),
+            LockfileError { line: 1 }
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! # Signatures.
+//!
+//! A library is signed with Ed25519 over its whole content, and verified against the public keys
+//! the host trusts before it is opened, see [`Libloading::enable_signature_verification`](super::libloading::Libloading::enable_signature_verification).
+//! Verification is offline: no certificate chain, revocation list or timestamp is involved.
+//!
+//! The signature is either embedded at the end of the library, see [`embed_signature`], or stored
+//! in a detached file next to it, named after the library with the [`SIGNATURE_EXTENSION`] extension
+//! appended, `libmy_plugin.so.sig` for example. The detached file holds the 64 bytes of the signature.
+//!
+//! An embedded signature takes precedence over a detached one.
+
+pub use ed25519_dalek::{Signature, VerifyingKey};
+
+/// Extension appended to the file name of a library to get the name of its detached signature.
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Marker ending a library with an embedded signature, preceded by the signature.
+pub const EMBEDDED_SIGNATURE_MAGIC: &[u8; 16] = b"RHAI-DYLIB-SIG-1";
+
+/// Append `signature`, computed over `library`, to `library`.
+///
+/// Dynamic linkers ignore data past the end of a library, which can still be opened once signed.
+#[must_use]
+pub fn embed_signature(library: &[u8], signature: &Signature) -> Vec<u8> {
+    [
+        library,
+        &signature.to_bytes(),
+        EMBEDDED_SIGNATURE_MAGIC.as_slice(),
+    ]
+    .concat()
+}
+
+/// Path of the detached signature of the library at `path`.
+#[must_use]
+pub fn detached_signature_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".");
+    signature.push(SIGNATURE_EXTENSION);
+    signature.into()
+}
+
+/// Error returned when the signature of a library cannot be verified.
+#[derive(Debug)]
+pub enum SignatureError {
+    /// The library is neither signed nor has a detached signature.
+    Unsigned,
+    /// The signature is not 64 bytes long.
+    Malformed,
+    /// The signature does not match the library for any of the trusted keys.
+    Untrusted,
+    /// The detached signature could not be read.
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsigned => write!(f, "library is not signed"),
+            Self::Malformed => write!(f, "malformed signature"),
+            Self::Untrusted => write!(f, "library is not signed by a trusted key"),
+            Self::Io(error) => write!(f, "failed to read signature: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Unsigned | Self::Malformed | Self::Untrusted => None,
+        }
+    }
+}
+
+/// Verify the content of a library against `trusted_keys`, with its embedded signature,
+/// or else the one read from `detached`.
+///
+/// # Errors
+///
+/// Returns an error if the library is not signed, or if its signature does not match any of the trusted keys.
+pub fn verify(
+    trusted_keys: &[VerifyingKey],
+    library: &[u8],
+    detached: Option<&std::path::Path>,
+) -> Result<(), SignatureError> {
+    let (library, signature) = if let Some((library, signature)) = split_embedded(library) {
+        (library, signature.to_vec())
+    } else {
+        let signature = detached.ok_or(SignatureError::Unsigned).and_then(|path| {
+            match std::fs::read(path) {
+                Ok(signature) => Ok(signature),
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                    Err(SignatureError::Unsigned)
+                }
+                Err(error) => Err(SignatureError::Io(error)),
+            }
+        })?;
+
+        (library, signature)
+    };
+
+    let signature = Signature::from_slice(&signature).map_err(|_| SignatureError::Malformed)?;
+
+    if trusted_keys
+        .iter()
+        .any(|key| key.verify_strict(library, &signature).is_ok())
+    {
+        Ok(())
+    } else {
+        Err(SignatureError::Untrusted)
+    }
+}
+
+/// Split a library with an embedded signature into its original content and the signature.
+fn split_embedded(library: &[u8]) -> Option<(&[u8], &[u8])> {
+    let library = library.strip_suffix(EMBEDDED_SIGNATURE_MAGIC.as_slice())?;
+    let split = library.len().checked_sub(Signature::BYTE_SIZE)?;
+
+    Some(library.split_at(split))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const LIBRARY: &[u8] = b"\x7fELF not really a library";
+
+    #[test]
+    fn verify_signatures() {
+        let trusted = SigningKey::from_bytes(&[1; 32]);
+        let other = SigningKey::from_bytes(&[2; 32]);
+        let keys = [trusted.verifying_key()];
+
+        let signed = embed_signature(LIBRARY, &trusted.sign(LIBRARY));
+        assert!(verify(&keys, &signed, None).is_ok());
+
+        let signed = embed_signature(LIBRARY, &other.sign(LIBRARY));
+        assert!(matches!(
+            verify(&keys, &signed, None),
+            Err(SignatureError::Untrusted)
+        ));
+
+        assert!(matches!(
+            verify(&keys, LIBRARY, None),
+            Err(SignatureError::Unsigned)
+        ));
+
+        let directory = std::env::temp_dir().join("rhai-dylib-signatures");
+        std::fs::create_dir_all(&directory).unwrap();
+        let detached = detached_signature_path(&directory.join("libplugin.so"));
+        assert_eq!(detached, directory.join("libplugin.so.sig"));
+
+        std::fs::write(&detached, trusted.sign(LIBRARY).to_bytes()).unwrap();
+        assert!(verify(&keys, LIBRARY, Some(&detached)).is_ok());
+        assert!(matches!(
+            verify(&keys, b"tampered", Some(&detached)),
+            Err(SignatureError::Untrusted)
+        ));
+
+        std::fs::write(&detached, b"short").unwrap();
+        assert!(matches!(
+            verify(&keys, LIBRARY, Some(&detached)),
+            Err(SignatureError::Malformed)
+        ));
+    }
+}
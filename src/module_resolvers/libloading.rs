@@ -1,6 +1,16 @@
-use super::{locked_read, locked_write};
-use crate::loader::libloading::Libloading;
-use crate::loader::Loader;
+use super::{locked_read, locked_write, SendSync};
+#[cfg(target_os = "linux")]
+use crate::loader::libloading::DlopenOptions;
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+use crate::loader::libloading::LinkNamespace;
+use crate::loader::libloading::{
+    EntrypointSymbol, HashingPolicy, Libloading, TypeIdPolicy, UnloadError,
+};
+#[cfg(feature = "pinning")]
+use crate::loader::pinning::DigestPins;
+#[cfg(feature = "signature")]
+use crate::loader::signature::VerifyingKey;
+use crate::loader::{LoadedPlugin, PluginHandle};
 
 #[cfg(target_os = "linux")]
 const DYLIB_EXTENSION: &str = "so";
@@ -9,6 +19,32 @@ const DYLIB_EXTENSION: &str = "dylib";
 #[cfg(target_os = "windows")]
 const DYLIB_EXTENSION: &str = "dll";
 
+/// Callback notified of library reloads, see [`DylibModuleResolver::on_reload`].
+#[cfg(not(feature = "sync"))]
+pub type ReloadCallback = Box<dyn Fn(&ReloadEvent)>;
+/// Callback notified of library reloads, see [`DylibModuleResolver::on_reload`].
+#[cfg(feature = "sync")]
+pub type ReloadCallback = Box<dyn Fn(&ReloadEvent) + Send + Sync>;
+
+/// A reload of a library that changed on disk.
+#[derive(Debug)]
+pub enum ReloadEvent<'a> {
+    /// The new build of the library has been loaded and replaces the cached module.
+    Reloaded {
+        /// Path of the library.
+        path: &'a std::path::Path,
+        /// The plugin loaded from the new build.
+        plugin: &'a LoadedPlugin,
+    },
+    /// The new build of the library could not be loaded, the cached module is kept.
+    Failed {
+        /// Path of the library.
+        path: &'a std::path::Path,
+        /// Why the new build could not be loaded.
+        error: &'a rhai::EvalAltResult,
+    },
+}
+
 /// A module resolver that load dynamic libraries pointed by the `import` path.
 pub struct DylibModuleResolver {
     /// Path prepended for each import if specified.
@@ -16,11 +52,54 @@ pub struct DylibModuleResolver {
     /// Is module caching enabled for this resolver.
     cache_enabled: bool,
     /// Cache of loaded modules, empty if [`Self::cache_enabled`] is false.
-    cache: rhai::Locked<std::collections::BTreeMap<std::path::PathBuf, rhai::Shared<rhai::Module>>>,
+    cache: rhai::Locked<std::collections::BTreeMap<ModuleKey, CachedModule>>,
+    /// Are libraries reloaded when they change on disk.
+    reload_enabled: bool,
+    /// Called each time a library is reloaded.
+    on_reload: Option<ReloadCallback>,
+    /// Configuration handed to libraries, by import path.
+    configs: std::collections::BTreeMap<String, rhai::Map>,
+    /// Libraries replaced by a new build, unloaded once their modules are dropped.
+    replaced: rhai::Locked<Vec<PluginHandle>>,
     /// Dynamic library loader.
     loader: rhai::Locked<Libloading>,
 }
 
+/// Identifies a module resolved from an import path.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ModuleKey {
+    /// Path of the library.
+    path: std::path::PathBuf,
+    /// Name of the module in the registry of the library, `None` for the module of the library.
+    name: Option<String>,
+}
+
+/// A module in the cache of the resolver.
+struct CachedModule {
+    handle: PluginHandle,
+    module: rhai::Shared<rhai::Module>,
+    /// Configuration handed to the library, handed again on reload.
+    config: rhai::Map,
+    /// State of the library file when the module was loaded, `None` if reloading is disabled.
+    file: Option<FileState>,
+}
+
+/// State of a library file, used to detect changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+}
+
+impl FileState {
+    fn read(path: &std::path::Path) -> Option<Self> {
+        std::fs::metadata(path).ok().map(|metadata| Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
 impl Default for DylibModuleResolver {
     fn default() -> Self {
         Self {
@@ -28,6 +107,10 @@ impl Default for DylibModuleResolver {
             loader: Libloading::new().into(),
             cache_enabled: true,
             cache: rhai::Locked::new(std::collections::BTreeMap::new()),
+            reload_enabled: false,
+            on_reload: None,
+            configs: std::collections::BTreeMap::new(),
+            replaced: rhai::Locked::new(vec![]),
         }
     }
 }
@@ -51,6 +134,229 @@ impl DylibModuleResolver {
         self.cache_enabled
     }
 
+    /// Enable/disable loading libraries from a copy, see [`Libloading::enable_shadow_copy`].
+    pub fn enable_shadow_copy(&mut self, enable: bool) -> &mut Self {
+        locked_write(&self.loader).enable_shadow_copy(enable);
+        self
+    }
+
+    /// Are libraries loaded from a copy?
+    #[must_use]
+    pub fn is_shadow_copy_enabled(&self) -> bool {
+        locked_read(&self.loader).is_shadow_copy_enabled()
+    }
+
+    /// Set the directory where libraries are copied, see [`Libloading::set_shadow_copy_directory`].
+    pub fn set_shadow_copy_directory(
+        &mut self,
+        directory: impl Into<std::path::PathBuf>,
+    ) -> &mut Self {
+        locked_write(&self.loader).set_shadow_copy_directory(directory);
+        self
+    }
+
+    /// Set the name of the host, see [`Libloading::set_host_name`].
+    pub fn set_host_name(&mut self, name: impl Into<String>) -> &mut Self {
+        locked_write(&self.loader).set_host_name(name);
+        self
+    }
+
+    /// Set the version of the host, see [`Libloading::set_host_version`].
+    pub fn set_host_version(&mut self, version: impl Into<String>) -> &mut Self {
+        locked_write(&self.loader).set_host_version(version);
+        self
+    }
+
+    /// Register a service handed to libraries, see [`Libloading::services_mut`].
+    pub fn register_service<T: rhai::Variant + Clone>(
+        &mut self,
+        name: impl Into<String>,
+        service: T,
+    ) -> &mut Self {
+        locked_write(&self.loader)
+            .services_mut()
+            .register(name, service);
+        self
+    }
+
+    /// Enable/disable catching panics in the functions of the plugin named `plugin`,
+    /// see [`Libloading::enable_panic_catching`].
+    pub fn enable_panic_catching(&mut self, plugin: impl Into<String>, enable: bool) -> &mut Self {
+        locked_write(&self.loader).enable_panic_catching(plugin, enable);
+        self
+    }
+
+    /// Are panics caught in the functions of the plugin named `plugin`?
+    #[must_use]
+    pub fn is_panic_catching_enabled(&self, plugin: &str) -> bool {
+        locked_read(&self.loader).is_panic_catching_enabled(plugin)
+    }
+
+    /// Enable/disable the compatibility check of libraries, see [`Libloading::enable_compatibility_check`].
+    pub fn enable_compatibility_check(&mut self, enable: bool) -> &mut Self {
+        locked_write(&self.loader).enable_compatibility_check(enable);
+        self
+    }
+
+    /// Is the compatibility check enabled?
+    #[must_use]
+    pub fn is_compatibility_check_enabled(&self) -> bool {
+        locked_read(&self.loader).is_compatibility_check_enabled()
+    }
+
+    /// Set what to do when the type ids of core Rhai types differ between a library and the host,
+    /// see [`Libloading::set_type_id_policy`].
+    ///
+    /// The resolver does not report the warnings of the libraries it loads.
+    pub fn set_type_id_policy(&mut self, policy: TypeIdPolicy) -> &mut Self {
+        locked_write(&self.loader).set_type_id_policy(policy);
+        self
+    }
+
+    /// What is done when the type ids of core Rhai types differ between a library and the host.
+    #[must_use]
+    pub fn type_id_policy(&self) -> TypeIdPolicy {
+        locked_read(&self.loader).type_id_policy()
+    }
+
+    /// Set what to do when a library hashes functions differently from the host,
+    /// see [`Libloading::set_hashing_policy`].
+    ///
+    /// The resolver does not report the warnings of the libraries it loads.
+    pub fn set_hashing_policy(&mut self, policy: HashingPolicy) -> &mut Self {
+        locked_write(&self.loader).set_hashing_policy(policy);
+        self
+    }
+
+    /// What is done when a library hashes functions differently from the host.
+    #[must_use]
+    pub fn hashing_policy(&self) -> HashingPolicy {
+        locked_read(&self.loader).hashing_policy()
+    }
+
+    /// Set the symbols of the entrypoints looked up in libraries, see [`Libloading::set_entrypoint_symbols`].
+    pub fn set_entrypoint_symbols(
+        &mut self,
+        symbols: impl IntoIterator<Item = EntrypointSymbol>,
+    ) -> &mut Self {
+        locked_write(&self.loader).set_entrypoint_symbols(symbols);
+        self
+    }
+
+    /// Symbols of the entrypoints looked up in libraries, in order.
+    #[must_use]
+    pub fn entrypoint_symbols(&self) -> Vec<EntrypointSymbol> {
+        locked_read(&self.loader).entrypoint_symbols().to_vec()
+    }
+
+    /// Set the flags passed to `dlopen` when opening libraries, see [`Libloading::set_dlopen_options`].
+    #[cfg(target_os = "linux")]
+    pub fn set_dlopen_options(&mut self, options: DlopenOptions) -> &mut Self {
+        locked_write(&self.loader).set_dlopen_options(options);
+        self
+    }
+
+    /// The flags passed to `dlopen` when opening libraries.
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn dlopen_options(&self) -> DlopenOptions {
+        locked_read(&self.loader).dlopen_options()
+    }
+
+    /// Set the link-map namespace libraries are opened in, see [`Libloading::set_link_namespace`].
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    pub fn set_link_namespace(&mut self, namespace: LinkNamespace) -> &mut Self {
+        locked_write(&self.loader).set_link_namespace(namespace);
+        self
+    }
+
+    /// The link-map namespace libraries are opened in.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[must_use]
+    pub fn link_namespace(&self) -> LinkNamespace {
+        locked_read(&self.loader).link_namespace().clone()
+    }
+
+    /// Enable/disable verifying the signature of libraries before opening them,
+    /// see [`Libloading::enable_signature_verification`].
+    #[cfg(feature = "signature")]
+    pub fn enable_signature_verification(&mut self, enable: bool) -> &mut Self {
+        locked_write(&self.loader).enable_signature_verification(enable);
+        self
+    }
+
+    /// Are the signatures of libraries verified before opening them?
+    #[cfg(feature = "signature")]
+    #[must_use]
+    pub fn is_signature_verification_enabled(&self) -> bool {
+        locked_read(&self.loader).is_signature_verification_enabled()
+    }
+
+    /// Trust libraries signed with `key`.
+    #[cfg(feature = "signature")]
+    pub fn add_trusted_key(&mut self, key: VerifyingKey) -> &mut Self {
+        locked_write(&self.loader).add_trusted_key(key);
+        self
+    }
+
+    /// Set the digests libraries must match to be opened, see [`Libloading::set_digest_pins`].
+    #[cfg(feature = "pinning")]
+    pub fn set_digest_pins(&mut self, pins: Option<DigestPins>) -> &mut Self {
+        locked_write(&self.loader).set_digest_pins(pins);
+        self
+    }
+
+    /// The digests libraries must match to be opened, if any.
+    #[cfg(feature = "pinning")]
+    #[must_use]
+    pub fn digest_pins(&self) -> Option<DigestPins> {
+        locked_read(&self.loader).digest_pins().cloned()
+    }
+
+    /// Set the configuration handed to the library imported with `import_path`,
+    /// see [`Libloading::load_with_config`].
+    ///
+    /// The import path is matched as written in the `import` statement.
+    pub fn set_plugin_config(
+        &mut self,
+        import_path: impl Into<String>,
+        config: rhai::Map,
+    ) -> &mut Self {
+        self.configs.insert(import_path.into(), config);
+        self
+    }
+
+    /// Configuration handed to the library imported with `import_path`.
+    #[must_use]
+    pub fn plugin_config(&self, import_path: &str) -> Option<&rhai::Map> {
+        self.configs.get(import_path)
+    }
+
+    /// Enable/disable reloading libraries that changed on disk.
+    ///
+    /// While reloading is enabled, libraries are loaded from a copy whatever [`Self::enable_shadow_copy`]
+    /// is set to: each build of a library is loaded from its own copy, so that a new build is never
+    /// confused with the previous one by the system. Cached modules are reloaded when their file changes,
+    /// either on the next `import` or when calling [`Self::reload_changed`].
+    ///
+    /// Modules of previous builds stay valid for as long as they are used.
+    pub fn enable_reload(&mut self, enable: bool) -> &mut Self {
+        self.reload_enabled = enable;
+        self
+    }
+
+    /// Is reloading enabled?
+    #[must_use]
+    pub const fn is_reload_enabled(&self) -> bool {
+        self.reload_enabled
+    }
+
+    /// Register a callback notified each time a library is reloaded, see [`ReloadEvent`].
+    pub fn on_reload(&mut self, callback: impl Fn(&ReloadEvent) + SendSync + 'static) -> &mut Self {
+        self.on_reload = Some(Box::new(callback));
+        self
+    }
+
     /// Create a new [`DylibModuleResolver`] with a specific base path.
     ///
     /// # Example
@@ -117,31 +423,178 @@ impl DylibModuleResolver {
             .or(source)
             .and_then(|p| std::path::Path::new(p).parent());
 
-        let path = self.get_file_path(path, source_path);
+        let config = self.configs.get(path).cloned().unwrap_or_default();
+        let file_path = self.get_file_path(path, source_path);
 
-        if !path.exists() {
-            return Err(Box::new(rhai::EvalAltResult::ErrorModuleNotFound(
-                path.to_str()
-                    .map_or_else(String::default, std::string::ToString::to_string),
-                position,
-            )));
-        }
+        let key = if file_path.exists() {
+            ModuleKey {
+                path: file_path,
+                name: None,
+            }
+        } else {
+            // `library/module` imports the module named `module` from the registry of `library`.
+            match path
+                .rsplit_once('/')
+                .map(|(library, name)| (self.get_file_path(library, source_path), name))
+            {
+                Some((library_path, name)) if library_path.exists() => ModuleKey {
+                    path: library_path,
+                    name: Some(name.to_string()),
+                },
+                _ => {
+                    return Err(Box::new(rhai::EvalAltResult::ErrorModuleNotFound(
+                        file_path
+                            .to_str()
+                            .map_or_else(String::default, std::string::ToString::to_string),
+                        position,
+                    )))
+                }
+            }
+        };
 
-        if self.is_cache_enabled() {
-            let module = { locked_read(&self.cache).get(&path).cloned() };
+        if !self.is_cache_enabled() {
+            return self.load(&key, config).map(|(plugin, _)| plugin.module);
+        }
 
-            if let Some(module) = module {
-                Ok(module)
-            } else {
-                let module = locked_write(&self.loader).load(path.as_path())?;
-                locked_write(&self.cache).insert(path, module.clone());
+        let cached = locked_read(&self.cache)
+            .get(&key)
+            .map(|cached| (cached.module.clone(), cached.file));
 
+        match cached {
+            Some((module, file)) if !self.reload_enabled || file == FileState::read(&key.path) => {
                 Ok(module)
             }
+            Some((module, _)) => Ok(self.reload(&key).unwrap_or(module)),
+            None => {
+                let (plugin, file) = self.load(&key, config.clone())?;
+
+                locked_write(&self.cache).insert(
+                    key,
+                    CachedModule {
+                        handle: plugin.handle,
+                        module: plugin.module.clone(),
+                        config,
+                        file,
+                    },
+                );
+
+                Ok(plugin.module)
+            }
+        }
+    }
+
+    /// Reload the cached libraries that changed on disk.
+    ///
+    /// Does nothing if reloading or the cache is disabled. Returns the number of reloaded libraries,
+    /// each reload is also reported to the callback registered with [`Self::on_reload`].
+    pub fn reload_changed(&self) -> usize {
+        if !self.reload_enabled {
+            return 0;
+        }
+
+        let changed = locked_read(&self.cache)
+            .iter()
+            .filter(|(key, cached)| cached.file != FileState::read(&key.path))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        changed
+            .into_iter()
+            .filter_map(|key| self.reload(&key))
+            .count()
+    }
+
+    /// Load the module identified by `key`, with the state of its library file if reloading is enabled.
+    fn load(
+        &self,
+        key: &ModuleKey,
+        config: rhai::Map,
+    ) -> Result<(LoadedPlugin, Option<FileState>), Box<rhai::EvalAltResult>> {
+        // Read before loading, so that a change made while loading triggers another reload.
+        let file = if self.reload_enabled {
+            FileState::read(&key.path)
         } else {
-            locked_write(&self.loader).load(path.as_path())
+            None
+        };
+
+        let mut loader = locked_write(&self.loader);
+
+        // Each build is loaded from its own copy while reloading, the setting of the loader is restored after.
+        let shadow_copy = loader.is_shadow_copy_enabled();
+        loader.enable_shadow_copy(shadow_copy || self.reload_enabled);
+
+        let plugin = match &key.name {
+            Some(name) => loader.load_module(&key.path, name, config),
+            None => loader.load_with_config(&key.path, config),
+        };
+
+        loader.enable_shadow_copy(shadow_copy);
+
+        Ok((plugin?, file))
+    }
+
+    /// Load the new build of a cached library and replace its module.
+    ///
+    /// Returns the new module, or `None` if the new build could not be loaded.
+    fn reload(&self, key: &ModuleKey) -> Option<rhai::Shared<rhai::Module>> {
+        let path = key.path.as_path();
+        let config = locked_read(&self.cache)
+            .get(key)
+            .map(|cached| cached.config.clone())
+            .unwrap_or_default();
+
+        match self.load(key, config.clone()) {
+            Ok((plugin, file)) => {
+                let previous = locked_write(&self.cache).insert(
+                    key.clone(),
+                    CachedModule {
+                        handle: plugin.handle,
+                        module: plugin.module.clone(),
+                        config,
+                        file,
+                    },
+                );
+
+                if let Some(previous) = previous {
+                    locked_write(&self.replaced).push(previous.handle);
+                }
+
+                self.unload_replaced();
+
+                if let Some(on_reload) = &self.on_reload {
+                    on_reload(&ReloadEvent::Reloaded {
+                        path,
+                        plugin: &plugin,
+                    });
+                }
+
+                Some(plugin.module)
+            }
+            Err(error) => {
+                // Keep serving the previous module, and do not retry until the file changes again.
+                if let Some(cached) = locked_write(&self.cache).get_mut(key) {
+                    cached.file = FileState::read(path);
+                }
+
+                if let Some(on_reload) = &self.on_reload {
+                    on_reload(&ReloadEvent::Failed {
+                        path,
+                        error: &error,
+                    });
+                }
+
+                None
+            }
         }
     }
+
+    /// Unload the libraries replaced by a new build whose modules are not used anymore.
+    fn unload_replaced(&self) {
+        let mut loader = locked_write(&self.loader);
+
+        locked_write(&self.replaced)
+            .retain(|handle| matches!(loader.unload(*handle), Err(UnloadError::InUse { .. })));
+    }
 }
 
 impl rhai::ModuleResolver for DylibModuleResolver {
@@ -188,14 +641,38 @@ mod tests {
         // Prevents multiple threads writing to the dll on windows and triggering a STATUS_ACCESS_VIOLATION error.
         static PATH: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
         PATH.get_or_init(|| {
+            // The loader hands this seed to the test plugin.
+            let _ = rhai::config::hashing::set_hashing_seed(Some([1, 2, 3, 4]));
+
+            // The plugins are built with the features of the host, to pass the compatibility check.
+            let features = [
+                ("sync", cfg!(feature = "sync")),
+                ("metadata", cfg!(feature = "metadata")),
+            ]
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| *feature)
+            .collect::<Vec<_>>()
+            .join(",");
+
             let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
             let status = std::process::Command::new("cargo")
-                .args(["build", "--example", "test_plugin"])
+                .args([
+                    "build",
+                    "--example",
+                    "test_plugin",
+                    "--example",
+                    "registry_plugin",
+                    "--example",
+                    "legacy_plugin",
+                    "--features",
+                    &features,
+                ])
                 .current_dir(manifest_dir)
                 .status()
                 .expect("failed to execute cargo build");
 
-            assert!(status.success(), "building test_plugin failed");
+            assert!(status.success(), "building test plugins failed");
 
             let target_dir = std::env::var("CARGO_TARGET_DIR")
                 .map(std::path::PathBuf::from)
@@ -218,6 +695,10 @@ mod tests {
             .replace('\\', "/")
     }
 
+    fn registry_plugin_module_path() -> String {
+        test_plugin_module_path().replace("test_plugin", "registry_plugin")
+    }
+
     #[test]
     fn new() {
         let mut r = DylibModuleResolver::new();
@@ -343,4 +824,198 @@ mod tests {
 
         assert_eq!(result, 3);
     }
+
+    #[test]
+    fn resolve_with_plugin_config() {
+        let module_path = test_plugin_module_path();
+        let mut config = rhai::Map::new();
+        config.insert("answer".into(), (42 as rhai::INT).into());
+
+        let mut r = DylibModuleResolver::new();
+        r.set_plugin_config(&module_path, config);
+        assert!(r.plugin_config(&module_path).is_some());
+
+        let mut engine = rhai::Engine::new();
+        engine.set_module_resolver(r);
+
+        let result = engine
+            .eval::<rhai::INT>(&format!(
+                r#"import "{module_path}" as p; p::config().answer"#
+            ))
+            .expect("engine eval failed");
+
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    fn resolve_module_from_registry() {
+        let module_path = registry_plugin_module_path();
+
+        let mut engine = rhai::Engine::new();
+        engine.set_module_resolver(DylibModuleResolver::new());
+
+        let result = engine
+            .eval::<String>(&format!(
+                r#"
+                    import "{module_path}/math" as math;
+                    import "{module_path}/text" as text;
+                    text::shout("hi") + math::square(3)
+                "#
+            ))
+            .expect("engine eval failed");
+
+        assert_eq!(result, "HI9");
+
+        let err = engine
+            .eval::<()>(&format!(r#"import "{module_path}/json" as json;"#))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("no module named `json`"));
+    }
+
+    #[test]
+    #[cfg(feature = "pinning")]
+    fn resolve_pinned_module() {
+        use crate::loader::pinning::{digest, DigestPins};
+
+        let module_path = test_plugin_module_path();
+        let library = std::fs::read(build_test_plugin()).expect("failed to read test_plugin");
+        let name = build_test_plugin().file_name().unwrap().to_string_lossy();
+
+        let mut resolver = DylibModuleResolver::new();
+        resolver.set_digest_pins(Some(
+            DigestPins::new()
+                .pin(name.clone(), digest(b"another build"))
+                .clone(),
+        ));
+
+        let mut engine = rhai::Engine::new();
+        engine.set_module_resolver(resolver);
+
+        let err = engine
+            .eval::<rhai::INT>(&format!(r#"import "{module_path}" as p; p::add(1, 2)"#))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("library digest mismatch"));
+
+        let mut resolver = DylibModuleResolver::new();
+        resolver.set_digest_pins(Some(DigestPins::new().pin(name, digest(&library)).clone()));
+        engine.set_module_resolver(resolver);
+
+        assert_eq!(
+            engine
+                .eval::<rhai::INT>(&format!(r#"import "{module_path}" as p; p::add(1, 2)"#))
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn resolve_legacy_plugin() {
+        let module_path = test_plugin_module_path().replace("test_plugin", "legacy_plugin");
+        let script = format!(r#"import "{module_path}" as p; p::answer()"#);
+
+        let mut engine = rhai::Engine::new();
+        engine.set_module_resolver(DylibModuleResolver::new());
+
+        assert!(engine.eval::<rhai::INT>(&script).is_err());
+
+        let mut resolver = DylibModuleResolver::new();
+        resolver
+            .enable_compatibility_check(false)
+            .set_type_id_policy(TypeIdPolicy::Ignore)
+            .set_hashing_policy(HashingPolicy::Ignore);
+        engine.set_module_resolver(resolver);
+
+        assert_eq!(engine.eval::<rhai::INT>(&script).unwrap(), 42);
+    }
+
+    #[test]
+    fn hashing_policy() {
+        let mut r = DylibModuleResolver::new();
+        assert_eq!(r.hashing_policy(), HashingPolicy::Deny);
+
+        r.set_hashing_policy(HashingPolicy::Warn);
+        assert_eq!(r.hashing_policy(), HashingPolicy::Warn);
+    }
+
+    #[test]
+    fn reload_toggle() {
+        let mut r = DylibModuleResolver::new();
+        assert!(!r.is_reload_enabled());
+
+        r.enable_reload(true);
+        assert!(r.is_reload_enabled());
+        assert!(!r.is_shadow_copy_enabled());
+
+        r.enable_shadow_copy(true).enable_reload(false);
+        assert!(r.is_shadow_copy_enabled());
+    }
+
+    #[test]
+    fn reload_changed_library() {
+        let directory =
+            std::env::temp_dir().join(format!("rhai-dylib-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let library = directory
+            .join("plugin")
+            .with_extension(build_test_plugin().extension().unwrap());
+        std::fs::copy(build_test_plugin(), &library).unwrap();
+
+        let reloads = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut r = DylibModuleResolver::with_path(&directory);
+        let engine = rhai::Engine::new();
+
+        r.enable_reload(true).on_reload({
+            let reloads = reloads.clone();
+            move |event| {
+                assert!(matches!(event, ReloadEvent::Reloaded { .. }));
+                reloads.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+
+        let m1 = r
+            .resolve(&engine, None, "plugin", rhai::Position::NONE)
+            .expect("first resolve failed");
+        let m2 = r
+            .resolve(&engine, None, "plugin", rhai::Position::NONE)
+            .expect("second resolve failed");
+
+        assert!(rhai::Shared::ptr_eq(&m1, &m2));
+        assert_eq!(r.reload_changed(), 0);
+
+        let eval = |module: &rhai::Shared<rhai::Module>, script: &str| {
+            let mut engine = rhai::Engine::new();
+            engine.register_static_module("p", module.clone());
+            engine.eval::<rhai::INT>(script)
+        };
+
+        assert_eq!(eval(&m1, "p::add(1, 2)").unwrap(), 3);
+
+        // Replace the library with another build.
+        let registry = build_test_plugin().with_file_name(
+            build_test_plugin()
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .replace("test_plugin", "registry_plugin"),
+        );
+        std::fs::copy(registry, &library).unwrap();
+
+        let m3 = r
+            .resolve(&engine, None, "plugin", rhai::Position::NONE)
+            .expect("resolve after change failed");
+
+        assert!(!rhai::Shared::ptr_eq(&m1, &m3));
+        assert_eq!(eval(&m3, "p::math::square(3)").unwrap(), 9);
+        assert!(eval(&m3, "p::add(1, 2)").is_err());
+        // The previous build is still loaded, as long as its module is used.
+        assert_eq!(eval(&m1, "p::add(1, 2)").unwrap(), 3);
+        assert_eq!(reloads.load(std::sync::atomic::Ordering::Relaxed), 1);
+        assert_eq!(r.reload_changed(), 0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
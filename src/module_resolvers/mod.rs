@@ -2,6 +2,18 @@
 #[cfg(feature = "libloading")]
 pub mod libloading;
 
+/// A trait to enforce `Send + Sync` on callbacks when the `sync` feature is enabled, like Rhai does.
+#[cfg(feature = "sync")]
+pub trait SendSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync> SendSync for T {}
+
+/// A trait to enforce `Send + Sync` on callbacks when the `sync` feature is enabled, like Rhai does.
+#[cfg(not(feature = "sync"))]
+pub trait SendSync {}
+#[cfg(not(feature = "sync"))]
+impl<T> SendSync for T {}
+
 // NOTE: Both of the following functions are rhai's internal and can't be accessed.
 // TODO: Ask to make this API public.
 
@@ -0,0 +1,462 @@
+//! # Compatibility.
+//!
+//! Rust does not have a stable ABI: a plugin built with another compiler, another rhai version or
+//! another set of rhai features does not share the layout of [`rhai::Module`] with its host, and
+//! calling its entrypoint is undefined behavior.
+//!
+//! Each plugin exports a [`Compatibility`] record describing its build environment. The host compares
+//! it with its own record before touching the entrypoint of the plugin.
+//!
+//! The record is exported by pointer: its first field is the version of its layout, which is the only
+//! field a host reads from a record of another version.
+
+use std::ffi::{c_char, CStr};
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Version of the [`Compatibility`] record layout.
+pub const COMPATIBILITY_VERSION: u32 = 1;
+
+/// Rhai features changing the layout of shared types, in the order of their bit in [`Compatibility`].
+///
+/// Only features that can be observed through the types of rhai are listed. The others are caught
+/// when they change the layout of the types shared with plugins.
+const RHAI_FEATURES: [&str; 4] = ["sync", "only_i32", "no_float", "f32_float"];
+
+/// Build environment of a plugin or of its host.
+///
+/// This record only uses C types so that it can be read even when the plugin
+/// has been built with another compiler.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Compatibility {
+    /// Layout version of this record, see [`COMPATIBILITY_VERSION`].
+    version: u32,
+    /// Was the crate built with debug assertions.
+    debug: bool,
+    /// Enabled rhai features, one bit per entry of [`RHAI_FEATURES`].
+    rhai_features: u64,
+    /// Fingerprint of the layout of the rhai types shared with plugins.
+    ///
+    /// Type ids are left out: they differ between builds of the same rhai crate, which is
+    /// checked by [`TypeIds`](super::type_ids::TypeIds) under its own policy.
+    rhai_layout: u64,
+    /// Output of `rustc --version`, nul terminated.
+    rustc_version: *const c_char,
+    /// Version of rhai-dylib, nul terminated.
+    rhai_dylib_version: *const c_char,
+}
+
+impl Compatibility {
+    /// Get the record of the current build.
+    ///
+    /// Called from a plugin, this describes the plugin. Called from the host, this describes the host.
+    #[must_use]
+    pub fn current() -> Self {
+        Self {
+            version: COMPATIBILITY_VERSION,
+            debug: cfg!(debug_assertions),
+            rhai_features: rhai_features(),
+            rhai_layout: rhai_layout(),
+            rustc_version: concat!(env!("RHAI_DYLIB_RUSTC_VERSION"), "\0")
+                .as_ptr()
+                .cast(),
+            rhai_dylib_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+        }
+    }
+
+    /// Get a pointer to the static record of the current build, as exported by plugins.
+    #[must_use]
+    pub fn record() -> *const Self {
+        static RECORD: OnceLock<Record> = OnceLock::new();
+
+        &raw const RECORD.get_or_init(|| Record(Self::current())).0
+    }
+
+    /// Compare the record of the host (`self`) with a record exported by a plugin.
+    ///
+    /// The version of the plugin record is read first, the rest of it is only read if it matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns every difference found between the host and the plugin.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` must be null or point to a record exported with [`Compatibility::record`],
+    /// of any version.
+    pub unsafe fn check(&self, plugin: *const Self) -> Result<(), IncompatiblePlugin> {
+        let version = if plugin.is_null() {
+            0
+        } else {
+            // SAFETY: every version of the record starts with its version, see the safety section.
+            unsafe { plugin.cast::<u32>().read() }
+        };
+
+        let mismatches = if version == self.version {
+            // SAFETY: the record has the layout of this version, see the safety section.
+            self.mismatches(unsafe { &*plugin })
+        } else {
+            vec![Mismatch::Version {
+                host: self.version,
+                plugin: version,
+            }]
+        };
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(IncompatiblePlugin { mismatches })
+        }
+    }
+
+    /// Version of the compiler used for this build.
+    #[must_use]
+    pub fn rustc_version(&self) -> &str {
+        c_str(self.rustc_version)
+    }
+
+    /// Version of rhai-dylib used for this build.
+    #[must_use]
+    pub fn rhai_dylib_version(&self) -> &str {
+        c_str(self.rhai_dylib_version)
+    }
+
+    /// Names of the rhai features enabled for this build.
+    pub fn rhai_features(&self) -> impl Iterator<Item = &'static str> {
+        feature_names(self.rhai_features)
+    }
+
+    /// Was this build made with debug assertions.
+    #[must_use]
+    pub const fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    /// List the differences between the record of the host (`self`) and the record of a plugin.
+    ///
+    /// An empty list means that the plugin can be loaded safely.
+    #[must_use]
+    pub fn mismatches(&self, plugin: &Self) -> Vec<Mismatch> {
+        // Nothing else can be read if the layouts differ.
+        if self.version != plugin.version {
+            return vec![Mismatch::Version {
+                host: self.version,
+                plugin: plugin.version,
+            }];
+        }
+
+        let mut mismatches = vec![];
+
+        if self.rustc_version() != plugin.rustc_version() {
+            mismatches.push(Mismatch::Rustc {
+                host: self.rustc_version().to_string(),
+                plugin: plugin.rustc_version().to_string(),
+            });
+        }
+
+        if self.rhai_dylib_version() != plugin.rhai_dylib_version() {
+            mismatches.push(Mismatch::RhaiDylib {
+                host: self.rhai_dylib_version().to_string(),
+                plugin: plugin.rhai_dylib_version().to_string(),
+            });
+        }
+
+        if self.rhai_layout != plugin.rhai_layout {
+            mismatches.push(Mismatch::Rhai);
+        }
+
+        if self.rhai_features != plugin.rhai_features {
+            mismatches.push(Mismatch::RhaiFeatures {
+                host: self.rhai_features().collect(),
+                plugin: plugin.rhai_features().collect(),
+            });
+        }
+
+        if self.debug != plugin.debug {
+            mismatches.push(Mismatch::Profile {
+                host_debug: self.debug,
+                plugin_debug: plugin.debug,
+            });
+        }
+
+        mismatches
+    }
+}
+
+/// Static record of the current build.
+struct Record(Compatibility);
+
+// SAFETY: the pointers of the record point to static strings, which are never written to.
+unsafe impl Send for Record {}
+unsafe impl Sync for Record {}
+
+/// A difference between the build environments of a host and a plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The compatibility records do not share the same layout.
+    Version {
+        /// Record version of the host.
+        host: u32,
+        /// Record version of the plugin.
+        plugin: u32,
+    },
+    /// The compilers are different.
+    Rustc {
+        /// Compiler version of the host.
+        host: String,
+        /// Compiler version of the plugin.
+        plugin: String,
+    },
+    /// The rhai-dylib versions are different.
+    RhaiDylib {
+        /// rhai-dylib version of the host.
+        host: String,
+        /// rhai-dylib version of the plugin.
+        plugin: String,
+    },
+    /// The rhai types shared with plugins have different layouts, either because of another
+    /// rhai version or of features not listed in the record.
+    Rhai,
+    /// The enabled rhai features are different.
+    RhaiFeatures {
+        /// Rhai features of the host.
+        host: Vec<&'static str>,
+        /// Rhai features of the plugin.
+        plugin: Vec<&'static str>,
+    },
+    /// One side was built in debug and the other one in release.
+    Profile {
+        /// Was the host built with debug assertions.
+        host_debug: bool,
+        /// Was the plugin built with debug assertions.
+        plugin_debug: bool,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Version { host, plugin } => write!(
+                f,
+                "compatibility record version mismatch (host: {host}, plugin: {plugin})"
+            ),
+            Self::Rustc { host, plugin } => {
+                write!(f, "rustc mismatch (host: {host}, plugin: {plugin})")
+            }
+            Self::RhaiDylib { host, plugin } => {
+                write!(f, "rhai-dylib mismatch (host: {host}, plugin: {plugin})")
+            }
+            Self::Rhai => write!(f, "rhai layout mismatch (different version or features)"),
+            Self::RhaiFeatures { host, plugin } => write!(
+                f,
+                "rhai features mismatch (host: [{}], plugin: [{}])",
+                host.join(", "),
+                plugin.join(", ")
+            ),
+            Self::Profile {
+                host_debug,
+                plugin_debug,
+            } => write!(
+                f,
+                "profile mismatch (host: {}, plugin: {})",
+                profile(*host_debug),
+                profile(*plugin_debug)
+            ),
+        }
+    }
+}
+
+/// Error returned when a plugin was built in an environment incompatible with its host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompatiblePlugin {
+    /// Every difference found between the host and the plugin.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl std::fmt::Display for IncompatiblePlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "plugin built in an incompatible environment: ")?;
+
+        for (index, mismatch) in self.mismatches.iter().enumerate() {
+            if index != 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{mismatch}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for IncompatiblePlugin {}
+
+/// Detect the rhai features of the current build.
+///
+/// Features are detected through the types of rhai, so that they are recorded
+/// even when enabled on rhai by another crate.
+fn rhai_features() -> u64 {
+    // Rhai stores its own floating-point type inline, and any other one as a custom type.
+    let f64_float = !rhai::Dynamic::from(0.0_f64).is_variant();
+    let f32_float = !rhai::Dynamic::from(0.0_f32).is_variant();
+
+    let enabled = [
+        std::any::type_name::<rhai::Shared<()>>().contains("Arc"),
+        std::mem::size_of::<rhai::INT>() == 4,
+        !f64_float && !f32_float,
+        f32_float,
+    ];
+
+    enabled
+        .iter()
+        .enumerate()
+        .filter(|(_, enabled)| **enabled)
+        .fold(0, |features, (bit, _)| features | 1 << bit)
+}
+
+/// Hash the size and alignment of the rhai types shared with plugins.
+fn rhai_layout() -> u64 {
+    let layouts = [
+        std::alloc::Layout::new::<rhai::Dynamic>(),
+        std::alloc::Layout::new::<rhai::ImmutableString>(),
+        std::alloc::Layout::new::<rhai::FnPtr>(),
+        std::alloc::Layout::new::<rhai::Module>(),
+        std::alloc::Layout::new::<rhai::Engine>(),
+        std::alloc::Layout::new::<rhai::Scope>(),
+        std::alloc::Layout::new::<rhai::EvalAltResult>(),
+    ];
+
+    let mut hasher = std::hash::DefaultHasher::new();
+    for layout in layouts {
+        layout.size().hash(&mut hasher);
+        layout.align().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Names of the features set in `features`.
+fn feature_names(features: u64) -> impl Iterator<Item = &'static str> {
+    RHAI_FEATURES
+        .iter()
+        .enumerate()
+        .filter(move |(bit, _)| features & 1 << bit != 0)
+        .map(|(_, name)| *name)
+}
+
+/// Hash the [`std::any::TypeId`] of a type.
+pub(crate) fn fingerprint<T: 'static>() -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    std::any::TypeId::of::<T>().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Read a nul terminated string of a compatibility record.
+fn c_str<'a>(ptr: *const c_char) -> &'a str {
+    if ptr.is_null() {
+        return "";
+    }
+
+    // SAFETY: records are created by `Compatibility::current`, which only stores pointers to
+    //         static nul terminated strings, and plugin records are read while their library is loaded.
+    unsafe { CStr::from_ptr(ptr) }.to_str().unwrap_or_default()
+}
+
+const fn profile(debug: bool) -> &'static str {
+    if debug {
+        "debug"
+    } else {
+        "release"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_is_compatible_with_itself() {
+        let current = Compatibility::current();
+
+        assert!(current.mismatches(&current).is_empty());
+        assert_eq!(current.rhai_dylib_version(), env!("CARGO_PKG_VERSION"));
+        assert!(!current.rustc_version().is_empty());
+    }
+
+    #[test]
+    fn features_are_detected() {
+        let features = Compatibility::current().rhai_features().collect::<Vec<_>>();
+
+        assert_eq!(features.contains(&"sync"), cfg!(feature = "sync"));
+        assert!(!(features.contains(&"no_float") && features.contains(&"f32_float")));
+    }
+
+    #[test]
+    fn mismatches_are_listed() {
+        let host = Compatibility::current();
+        let plugin = Compatibility {
+            debug: !host.debug,
+            rhai_features: host.rhai_features ^ 0b10,
+            rustc_version: c"rustc 0.0.0".as_ptr(),
+            ..host
+        };
+
+        let mismatches = host.mismatches(&plugin);
+
+        assert_eq!(mismatches.len(), 3);
+        assert!(
+            matches!(&mismatches[0], Mismatch::Rustc { plugin, .. } if plugin == "rustc 0.0.0")
+        );
+        assert!(matches!(&mismatches[1], Mismatch::RhaiFeatures { .. }));
+        assert!(matches!(&mismatches[2], Mismatch::Profile { .. }));
+    }
+
+    #[test]
+    fn layout_mismatch_is_listed() {
+        let host = Compatibility::current();
+        let plugin = Compatibility {
+            rhai_layout: !host.rhai_layout,
+            ..host
+        };
+
+        assert_eq!(host.mismatches(&plugin), vec![Mismatch::Rhai]);
+    }
+
+    #[test]
+    fn exported_record_is_checked() {
+        let host = Compatibility::current();
+        let newer = Compatibility {
+            version: COMPATIBILITY_VERSION + 1,
+            ..host
+        };
+
+        assert_eq!(unsafe { host.check(Compatibility::record()) }, Ok(()));
+        assert_eq!(
+            unsafe { host.check(&raw const newer) }
+                .unwrap_err()
+                .mismatches,
+            vec![Mismatch::Version {
+                host: COMPATIBILITY_VERSION,
+                plugin: COMPATIBILITY_VERSION + 1,
+            }]
+        );
+        assert!(unsafe { host.check(std::ptr::null()) }.is_err());
+    }
+
+    #[test]
+    fn version_mismatch_stops_comparison() {
+        let host = Compatibility::current();
+        let plugin = Compatibility {
+            version: COMPATIBILITY_VERSION + 1,
+            rustc_version: std::ptr::null(),
+            ..host
+        };
+
+        assert_eq!(
+            host.mismatches(&plugin),
+            vec![Mismatch::Version {
+                host: COMPATIBILITY_VERSION,
+                plugin: COMPATIBILITY_VERSION + 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! # Host context.
+//!
+//! Plugins exporting the [`MODULE_ENTRYPOINT_V2`](super::MODULE_ENTRYPOINT_V2) entrypoint receive a [`HostContext`]
+//! describing the host they are loaded into, and giving them access to the services registered by the host.
+
+/// Description of the host loading a plugin, handed to the entrypoint of the plugin.
+#[derive(Debug, Clone, Default)]
+pub struct HostContext {
+    host_name: String,
+    host_version: String,
+    rhai_dylib_version: String,
+    config: rhai::Map,
+    services: Services,
+    panic_catching: bool,
+}
+
+impl HostContext {
+    /// Create the context of the current host.
+    #[must_use]
+    pub fn new(
+        host_name: impl Into<String>,
+        host_version: impl Into<String>,
+        config: rhai::Map,
+        services: Services,
+    ) -> Self {
+        Self {
+            host_name: host_name.into(),
+            host_version: host_version.into(),
+            rhai_dylib_version: env!("CARGO_PKG_VERSION").to_string(),
+            config,
+            services,
+            panic_catching: false,
+        }
+    }
+
+    /// Ask the plugin to catch panics of the functions of its module, see [`super::panics`].
+    pub fn enable_panic_catching(&mut self, enable: bool) -> &mut Self {
+        self.panic_catching = enable;
+        self
+    }
+
+    /// Should the plugin catch panics of the functions of its module?
+    #[must_use]
+    pub const fn is_panic_catching_enabled(&self) -> bool {
+        self.panic_catching
+    }
+
+    /// Name of the host.
+    #[must_use]
+    pub fn host_name(&self) -> &str {
+        &self.host_name
+    }
+
+    /// Version of the host.
+    #[must_use]
+    pub fn host_version(&self) -> &str {
+        &self.host_version
+    }
+
+    /// Version of rhai-dylib used by the host.
+    #[must_use]
+    pub fn rhai_dylib_version(&self) -> &str {
+        &self.rhai_dylib_version
+    }
+
+    /// Configuration of the plugin.
+    #[must_use]
+    pub const fn config(&self) -> &rhai::Map {
+        &self.config
+    }
+
+    /// Services registered by the host.
+    #[must_use]
+    pub const fn services(&self) -> &Services {
+        &self.services
+    }
+}
+
+/// Values shared by a host with its plugins, identified by name.
+///
+/// A service can be any value Rhai can store, shared objects such as
+/// `rhai::Shared<dyn Logger>` included.
+#[derive(Debug, Clone, Default)]
+pub struct Services(std::collections::BTreeMap<String, rhai::Dynamic>);
+
+impl Services {
+    /// Create an empty set of services.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a service, replacing any service registered with the same name.
+    pub fn register<T: rhai::Variant + Clone>(
+        &mut self,
+        name: impl Into<String>,
+        service: T,
+    ) -> &mut Self {
+        self.0.insert(name.into(), rhai::Dynamic::from(service));
+        self
+    }
+
+    /// Get a service by name, `None` if it is not registered or is not of type `T`.
+    #[must_use]
+    pub fn get<T: rhai::Variant + Clone>(&self, name: &str) -> Option<T> {
+        self.0
+            .get(name)
+            .and_then(|service| service.clone().try_cast())
+    }
+
+    /// Is a service registered with this name?
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services() {
+        let mut services = Services::new();
+
+        services.register("answer", 42 as rhai::INT);
+
+        assert!(services.contains("answer"));
+        assert_eq!(services.get::<rhai::INT>("answer"), Some(42));
+        assert_eq!(services.get::<bool>("answer"), None);
+        assert_eq!(services.get::<rhai::INT>("question"), None);
+    }
+
+    #[test]
+    fn context() {
+        let context = HostContext::new("host", "1.0.0", rhai::Map::new(), Services::new());
+
+        assert_eq!(context.host_name(), "host");
+        assert_eq!(context.host_version(), "1.0.0");
+        assert_eq!(context.rhai_dylib_version(), env!("CARGO_PKG_VERSION"));
+        assert!(context.config().is_empty());
+        assert!(!context.is_panic_catching_enabled());
+    }
+}
@@ -0,0 +1,504 @@
+//! # C ABI.
+//!
+//! Plugins exporting the [`MODULE_ENTRYPOINT_V2`](super::MODULE_ENTRYPOINT_V2) entrypoint hand a [`rhai::Module`]
+//! to the host: both must be built with the same compiler and the same Rhai build.
+//!
+//! Plugins exporting the [`MODULE_FFI_ENTRYPOINT`](super::MODULE_FFI_ENTRYPOINT) entrypoint instead only use the
+//! C-compatible types of this module. The host hands them an [`FfiHost`] table of functions through which
+//! they register functions taking and returning [`FfiValue`]s, and builds the [`rhai::Module`] itself.
+//! They can be built with another toolchain, or written in C, C++, Zig or any language exporting C functions.
+//!
+//! Besides their entrypoint, those plugins can export the [`Metadata`](super::metadata::Metadata) record,
+//! see its C layout, and are otherwise named after the file stem of their library. The compatibility,
+//! type ids and hashing checks do not apply to them.
+//!
+//! ```c
+//! typedef struct { const uint8_t *ptr; size_t len; } FfiStr;
+//!
+//! typedef struct {
+//!     uint8_t tag; // 0: unit, 1: bool, 2: int, 3: float, 4: string.
+//!     union { uint8_t boolean; int64_t integer; double number; FfiStr string; };
+//! } FfiValue;
+//!
+//! void add(void *user_data, const FfiValue *args, size_t num_args, FfiCall *call, const FfiHost *host) {
+//!     FfiValue result = { .tag = 2, .integer = args[0].integer + args[1].integer };
+//!     host->return_value(call, result);
+//! }
+//!
+//! void module_ffi_entrypoint(const FfiHost *host, FfiModule *module) {
+//!     host->register_fn(module, (FfiStr) { (const uint8_t *) "add", 3 }, 2, add, NULL);
+//! }
+//! ```
+//!
+//! Strings are not null-terminated. Strings handed by the host are only valid during the call,
+//! and strings handed to the host are copied before the host function returns. A value handed to the
+//! host with an unknown tag makes the call fail with a runtime error.
+
+use std::ffi::c_void;
+
+/// Version of the [`FfiHost`] table. New functions are only ever added at the end of the table.
+pub const FFI_VERSION: u32 = 1;
+
+/// Tag of an [`FfiValue`] holding `()`.
+pub const FFI_UNIT: u8 = 0;
+/// Tag of an [`FfiValue`] holding a boolean, in [`FfiPayload::boolean`].
+pub const FFI_BOOL: u8 = 1;
+/// Tag of an [`FfiValue`] holding an integer, in [`FfiPayload::integer`].
+pub const FFI_INT: u8 = 2;
+/// Tag of an [`FfiValue`] holding a floating-point number, in [`FfiPayload::number`].
+pub const FFI_FLOAT: u8 = 3;
+/// Tag of an [`FfiValue`] holding a string, in [`FfiPayload::string`].
+pub const FFI_STRING: u8 = 4;
+
+/// Prototype of the entrypoint of a plugin using the C ABI, registering its functions through `host`.
+pub type FfiEntrypoint = extern "C" fn(host: &FfiHost, module: *mut FfiModule);
+
+/// Prototype of a function registered by a plugin using the C ABI.
+///
+/// The function reads `num_args` arguments from `args` and sets its result with [`FfiHost::return_value`]
+/// or [`FfiHost::return_error`], the result defaulting to `()`. `user_data` is the pointer given
+/// to [`FfiHost::register_fn`].
+pub type FfiFn = extern "C" fn(
+    user_data: *mut c_void,
+    args: *const FfiValue,
+    num_args: usize,
+    call: *mut FfiCall,
+    host: &FfiHost,
+);
+
+/// A string shared between a plugin and its host, not null-terminated.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiStr {
+    /// Pointer to the UTF-8 bytes of the string.
+    pub ptr: *const u8,
+    /// Number of bytes of the string.
+    pub len: usize,
+}
+
+impl FfiStr {
+    /// Borrow a string, which must outlive the use of the result.
+    #[must_use]
+    pub const fn new(value: &str) -> Self {
+        Self {
+            ptr: value.as_ptr(),
+            len: value.len(),
+        }
+    }
+
+    /// Read the string, `None` if it is not valid UTF-8.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` readable bytes, valid for `'a`.
+    #[must_use]
+    pub unsafe fn as_str<'a>(self) -> Option<&'a str> {
+        if self.len == 0 {
+            return Some("");
+        }
+
+        std::str::from_utf8(std::slice::from_raw_parts(self.ptr, self.len)).ok()
+    }
+}
+
+/// A value exchanged between a plugin and its host.
+///
+/// The tag is a plain byte, so that a value with an unknown tag, handed by a plugin written in another
+/// language, can be detected instead of being undefined behavior. Use [`FfiValue::read`] to read it.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FfiValue {
+    /// Kind of the value, one of [`FFI_UNIT`], [`FFI_BOOL`], [`FFI_INT`], [`FFI_FLOAT`] or [`FFI_STRING`].
+    pub tag: u8,
+    /// The value, in the field selected by the tag.
+    pub payload: FfiPayload,
+}
+
+/// Payload of an [`FfiValue`], selected by its tag.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union FfiPayload {
+    /// A boolean, `0` being `false` and any other value `true`.
+    pub boolean: u8,
+    /// An integer.
+    pub integer: i64,
+    /// A floating-point number.
+    pub number: f64,
+    /// A string.
+    pub string: FfiStr,
+}
+
+/// The content of an [`FfiValue`] with a known tag.
+#[derive(Debug, Clone, Copy)]
+pub enum FfiData {
+    /// `()`.
+    Unit,
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    Int(i64),
+    /// A floating-point number.
+    Float(f64),
+    /// A string.
+    String(FfiStr),
+}
+
+impl FfiValue {
+    /// Read the content of the value.
+    ///
+    /// # Errors
+    ///
+    /// Returns the tag of the value if it is unknown.
+    pub fn read(&self) -> Result<FfiData, u8> {
+        // SAFETY: the field selected by the tag is the one written with it, and every bit pattern is valid for each field.
+        unsafe {
+            match self.tag {
+                FFI_UNIT => Ok(FfiData::Unit),
+                FFI_BOOL => Ok(FfiData::Bool(self.payload.boolean != 0)),
+                FFI_INT => Ok(FfiData::Int(self.payload.integer)),
+                FFI_FLOAT => Ok(FfiData::Float(self.payload.number)),
+                FFI_STRING => Ok(FfiData::String(self.payload.string)),
+                tag => Err(tag),
+            }
+        }
+    }
+}
+
+impl From<FfiData> for FfiValue {
+    fn from(data: FfiData) -> Self {
+        let (tag, payload) = match data {
+            FfiData::Unit => (FFI_UNIT, FfiPayload { integer: 0 }),
+            FfiData::Bool(value) => (
+                FFI_BOOL,
+                FfiPayload {
+                    boolean: value.into(),
+                },
+            ),
+            FfiData::Int(value) => (FFI_INT, FfiPayload { integer: value }),
+            FfiData::Float(value) => (FFI_FLOAT, FfiPayload { number: value }),
+            FfiData::String(value) => (FFI_STRING, FfiPayload { string: value }),
+        };
+
+        Self { tag, payload }
+    }
+}
+
+impl std::fmt::Debug for FfiValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.read() {
+            Ok(data) => data.fmt(f),
+            Err(tag) => write!(f, "FfiValue {{ tag: {tag} }}"),
+        }
+    }
+}
+
+/// Functions of the host called by plugins using the C ABI.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiHost {
+    /// Version of the table, see [`FFI_VERSION`].
+    pub version: u32,
+    /// Register a function called `name`, taking `num_params` parameters, in the module being built.
+    pub register_fn: extern "C" fn(
+        module: *mut FfiModule,
+        name: FfiStr,
+        num_params: usize,
+        function: FfiFn,
+        user_data: *mut c_void,
+    ),
+    /// Fail to build the module, with an error message.
+    pub fail: extern "C" fn(module: *mut FfiModule, message: FfiStr),
+    /// Set the result of a function call.
+    pub return_value: extern "C" fn(call: *mut FfiCall, value: FfiValue),
+    /// Set the result of a function call to an error, with an error message.
+    pub return_error: extern "C" fn(call: *mut FfiCall, message: FfiStr),
+}
+
+/// Module being built by the host, opaque to plugins.
+#[derive(Debug, Default)]
+pub struct FfiModule {
+    module: rhai::Module,
+    error: Option<String>,
+}
+
+/// Result of a function call, opaque to plugins.
+#[derive(Debug, Default)]
+pub struct FfiCall {
+    result: Option<Result<rhai::Dynamic, String>>,
+}
+
+/// Pointer given by a plugin along with a function, owned by the plugin.
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+
+// SAFETY: the pointer is only handed back to the plugin, which is responsible for its thread safety.
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+static HOST: FfiHost = FfiHost {
+    version: FFI_VERSION,
+    register_fn,
+    fail,
+    return_value,
+    return_error,
+};
+
+/// Build the module of a plugin using the C ABI, calling its `entrypoint`.
+///
+/// # Errors
+///
+/// Returns the message given by the plugin if it failed to build its module.
+#[cfg_attr(not(feature = "libloading"), allow(dead_code))]
+pub(crate) fn build_module(entrypoint: FfiEntrypoint) -> Result<rhai::Module, String> {
+    let mut module = FfiModule::default();
+
+    entrypoint(&HOST, &raw mut module);
+
+    module.error.map_or(Ok(module.module), Err)
+}
+
+/// Read a string handed by a plugin, replacing invalid UTF-8.
+fn read_str(value: FfiStr) -> String {
+    if value.len == 0 {
+        return String::new();
+    }
+
+    // SAFETY: plugins hand strings valid for the duration of the call.
+    String::from_utf8_lossy(unsafe { std::slice::from_raw_parts(value.ptr, value.len) })
+        .into_owned()
+}
+
+extern "C" fn register_fn(
+    module: *mut FfiModule,
+    name: FfiStr,
+    num_params: usize,
+    function: FfiFn,
+    user_data: *mut c_void,
+) {
+    // SAFETY: plugins only receive pointers to modules built by `build_module`.
+    let module = unsafe { &mut *module };
+    let name = read_str(name);
+    let user_data = UserData(user_data);
+
+    rhai::FuncRegistration::new(name).set_into_module_raw(
+        &mut module.module,
+        vec![std::any::TypeId::of::<rhai::Dynamic>(); num_params],
+        rhai::plugin::RhaiFunc::Pure {
+            func: rhai::Shared::new(move |context, args| {
+                let position = context
+                    .as_ref()
+                    .map_or(rhai::Position::NONE, rhai::NativeCallContext::call_position);
+
+                call(function, user_data, args).map_err(|message| {
+                    rhai::EvalAltResult::ErrorRuntime(message.into(), position).into()
+                })
+            }),
+            has_context: false,
+            is_pure: true,
+            is_volatile: true,
+        },
+    );
+}
+
+extern "C" fn fail(module: *mut FfiModule, message: FfiStr) {
+    // SAFETY: plugins only receive pointers to modules built by `build_module`.
+    let module = unsafe { &mut *module };
+    module.error = Some(read_str(message));
+}
+
+extern "C" fn return_value(call: *mut FfiCall, value: FfiValue) {
+    // SAFETY: plugins only receive pointers to calls made by `call`.
+    let call = unsafe { &mut *call };
+    call.result = Some(to_dynamic(value));
+}
+
+extern "C" fn return_error(call: *mut FfiCall, message: FfiStr) {
+    // SAFETY: plugins only receive pointers to calls made by `call`.
+    let call = unsafe { &mut *call };
+    call.result = Some(Err(read_str(message)));
+}
+
+/// Call a function registered by a plugin with the arguments of a script call.
+fn call(
+    function: FfiFn,
+    user_data: UserData,
+    args: &mut [&mut rhai::Dynamic],
+) -> Result<rhai::Dynamic, String> {
+    // Strings handed to the plugin point into these values, which outlive the call.
+    let strings = args
+        .iter()
+        .map(|arg| rhai::Dynamic::clone(arg).into_immutable_string().ok())
+        .collect::<Vec<_>>();
+
+    let values = args
+        .iter()
+        .zip(&strings)
+        .map(|(arg, string)| match string {
+            Some(string) => Ok(FfiData::String(FfiStr::new(string)).into()),
+            None => to_ffi_value(arg),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut call = FfiCall::default();
+
+    function(
+        user_data.0,
+        values.as_ptr(),
+        values.len(),
+        &raw mut call,
+        &HOST,
+    );
+
+    call.result.unwrap_or(Ok(rhai::Dynamic::UNIT))
+}
+
+// `rhai::INT` is smaller with the `only_i32` feature.
+#[allow(clippy::useless_conversion)]
+fn to_ffi_value(value: &rhai::Dynamic) -> Result<FfiValue, String> {
+    if value.is_unit() {
+        return Ok(FfiData::Unit.into());
+    }
+
+    if let Ok(value) = value.as_bool() {
+        return Ok(FfiData::Bool(value).into());
+    }
+
+    if let Ok(value) = value.as_int() {
+        return Ok(FfiData::Int(value.into()).into());
+    }
+
+    // Floating-point numbers are `f32` with the `f32_float` feature of rhai.
+    if let Some(value) = value.clone().try_cast::<f64>() {
+        return Ok(FfiData::Float(value).into());
+    }
+
+    if let Some(value) = value.clone().try_cast::<f32>() {
+        return Ok(FfiData::Float(value.into()).into());
+    }
+
+    Err(format!(
+        "values of type {} cannot be handed to a plugin",
+        value.type_name()
+    ))
+}
+
+fn to_dynamic(value: FfiValue) -> Result<rhai::Dynamic, String> {
+    match value
+        .read()
+        .map_err(|tag| format!("value with unknown tag {tag}"))?
+    {
+        FfiData::Unit => Ok(rhai::Dynamic::UNIT),
+        FfiData::Bool(value) => Ok(value.into()),
+        FfiData::Int(value) => rhai::INT::try_from(value)
+            .map(Into::into)
+            .map_err(|_| format!("integer {value} is out of range")),
+        FfiData::Float(value) => to_float(value),
+        FfiData::String(value) => Ok(read_str(value).into()),
+    }
+}
+
+/// Convert a floating-point number to the floating-point type of rhai, which is not named
+/// with the `no_float` feature.
+fn to_float(value: f64) -> Result<rhai::Dynamic, String> {
+    // Rhai stores its own floating-point type inline, and any other one as a custom type.
+    let float = rhai::Dynamic::from(value);
+
+    if !float.is_variant() {
+        return Ok(float);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    let float = rhai::Dynamic::from(value as f32);
+
+    if !float.is_variant() {
+        return Ok(float);
+    }
+
+    Err("floating-point numbers are not supported".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn concat(
+        _: *mut c_void,
+        args: *const FfiValue,
+        num_args: usize,
+        call: *mut FfiCall,
+        host: &FfiHost,
+    ) {
+        let args = unsafe { std::slice::from_raw_parts(args, num_args) };
+
+        match args.iter().map(FfiValue::read).collect::<Vec<_>>()[..] {
+            [Ok(FfiData::String(a)), Ok(FfiData::Int(b))] => {
+                let result = format!("{}{b}", unsafe { a.as_str() }.unwrap_or_default());
+                (host.return_value)(call, FfiData::String(FfiStr::new(&result)).into());
+            }
+            _ => (host.return_error)(call, FfiStr::new("expected a string and an integer")),
+        }
+    }
+
+    extern "C" fn unknown_tag(
+        _: *mut c_void,
+        _: *const FfiValue,
+        _: usize,
+        call: *mut FfiCall,
+        host: &FfiHost,
+    ) {
+        let value = FfiValue {
+            tag: 42,
+            payload: FfiPayload { integer: 0 },
+        };
+        (host.return_value)(call, value);
+    }
+
+    extern "C" fn entrypoint(host: &FfiHost, module: *mut FfiModule) {
+        (host.register_fn)(
+            module,
+            FfiStr::new("concat"),
+            2,
+            concat,
+            std::ptr::null_mut(),
+        );
+        (host.register_fn)(
+            module,
+            FfiStr::new("unknown_tag"),
+            0,
+            unknown_tag,
+            std::ptr::null_mut(),
+        );
+    }
+
+    extern "C" fn failing_entrypoint(host: &FfiHost, module: *mut FfiModule) {
+        (host.fail)(module, FfiStr::new("no resource"));
+    }
+
+    #[test]
+    fn build_and_call() {
+        let module = build_module(entrypoint).expect("failed to build the module");
+
+        let mut engine = rhai::Engine::new();
+        engine.register_global_module(module.into());
+
+        assert_eq!(
+            engine.eval::<String>(r#"concat("answer: ", 42)"#).unwrap(),
+            "answer: 42"
+        );
+
+        let err = engine.eval::<String>("concat(1, 2)").unwrap_err();
+
+        assert!(matches!(*err, rhai::EvalAltResult::ErrorRuntime(..)));
+        assert!(err.to_string().contains("expected a string and an integer"));
+
+        let err = engine.eval::<rhai::Dynamic>("unknown_tag()").unwrap_err();
+
+        assert!(matches!(*err, rhai::EvalAltResult::ErrorRuntime(..)));
+        assert!(err.to_string().contains("unknown tag 42"));
+    }
+
+    #[test]
+    fn build_error() {
+        assert_eq!(build_module(failing_entrypoint).unwrap_err(), "no resource");
+    }
+}
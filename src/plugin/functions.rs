@@ -0,0 +1,237 @@
+//! # Functions.
+//!
+//! Functions of a module can only be enumerated through an engine, and only be replaced by
+//! registering them again, which computes their hashes with the hashing configuration of the current build.
+
+use rhai::plugin::RhaiFunc;
+use rhai::{FnAccess, FnNamespace, FuncMetadata, FuncRegistration, Module};
+
+use super::hashing::DroppedItems;
+
+/// Register again every function of `module` and of its sub-modules, replacing native functions
+/// by the result of `map` when it returns one.
+///
+/// Functions are hashed by the current build. If this changes their hashes, the module is rebuilt from
+/// its functions, variables, custom types and sub-modules. Type iterators and private native functions
+/// cannot be listed, they are left out and counted in the returned [`DroppedItems`]. Custom types can
+/// only be listed with the `metadata` feature, and are lost without it.
+pub(crate) fn map_functions(
+    module: rhai::Shared<Module>,
+    map: &impl Fn(&FuncMetadata, &RhaiFunc) -> Option<RhaiFunc>,
+) -> (rhai::Shared<Module>, DroppedItems) {
+    let module = rhai::Shared::unwrap_or_clone(module);
+
+    let functions = functions(&module)
+        .into_iter()
+        .map(|(metadata, func)| {
+            let func = if func.is_script() {
+                func
+            } else {
+                map(&metadata, &func).unwrap_or(func)
+            };
+
+            (metadata, func)
+        })
+        .collect::<Vec<_>>();
+
+    let mut rebuilt = module.clone();
+    let mut dropped = DroppedItems::default();
+
+    if !set_functions(&mut rebuilt, &functions) {
+        // Functions cannot be removed from a module, those keyed by their previous hashes would remain.
+        rebuilt = Module::new();
+
+        if let Some(id) = module.id() {
+            rebuilt.set_id(id);
+        }
+
+        #[cfg(feature = "metadata")]
+        rebuilt.set_doc(module.doc());
+
+        for (name, value) in module.iter_var() {
+            rebuilt.set_var(name, value.clone());
+        }
+
+        #[cfg(feature = "metadata")]
+        copy_custom_types(&module, &mut rebuilt);
+
+        set_functions(&mut rebuilt, &functions);
+
+        let (_, function_count, type_iterators) = module.count();
+        dropped.type_iterators = type_iterators;
+        dropped.private_functions = function_count.saturating_sub(functions.len());
+    }
+
+    // Sub-modules can be used on their own, such as the modules of a registry.
+    for (name, sub_module) in module.iter_sub_modules() {
+        let (sub_module, sub_module_dropped) = map_functions(sub_module.clone(), map);
+
+        rebuilt.set_sub_module(name, sub_module);
+        dropped.type_iterators += sub_module_dropped.type_iterators;
+        dropped.private_functions += sub_module_dropped.private_functions;
+    }
+
+    (rebuilt.into(), dropped)
+}
+
+/// Copy the custom types of `module`, without those of its sub-modules, to `rebuilt`.
+///
+/// Modules do not list their custom types, only the metadata of an engine does.
+#[cfg(feature = "metadata")]
+fn copy_custom_types(module: &Module, rebuilt: &mut Module) {
+    let mut engine = rhai::Engine::new_raw();
+    engine.register_global_module(module.clone().into());
+
+    let Some(metadata) = engine
+        .gen_fn_metadata_to_json(false)
+        .ok()
+        .and_then(|json| engine.parse_json(json, true).ok())
+    else {
+        return;
+    };
+
+    let custom_types = metadata
+        .get("customTypes")
+        .and_then(|types| types.clone().try_cast::<rhai::Array>())
+        .unwrap_or_default();
+
+    for custom_type in custom_types {
+        let Some(custom_type) = custom_type.try_cast::<rhai::Map>() else {
+            continue;
+        };
+        let field = |name: &str| {
+            custom_type
+                .get(name)
+                .and_then(|value| value.clone().into_string().ok())
+        };
+        let (Some(type_name), Some(display_name)) = (field("typeName"), field("displayName"))
+        else {
+            continue;
+        };
+        let comments = custom_type
+            .get("docComments")
+            .and_then(|comments| comments.clone().try_cast::<rhai::Array>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|comment| comment.into_string().ok());
+
+        rebuilt.set_custom_type_with_comments_raw(type_name, display_name, comments);
+    }
+}
+
+/// List the public native functions and the script-defined functions of `module`, without those of its sub-modules.
+fn functions(module: &Module) -> Vec<(FuncMetadata, RhaiFunc)> {
+    let mut engine = rhai::Engine::new_raw();
+    engine.register_global_module(module.clone().into());
+
+    let functions = engine.collect_fn_metadata(
+        None,
+        |info| {
+            (info.metadata.access == FnAccess::Public || info.script.is_some())
+                .then(|| info.metadata.clone())
+        },
+        false,
+    );
+
+    // Indexing keys functions of the global namespace by their plain hash.
+    // The namespace cannot be changed through `FuncRegistration` without the function itself.
+    let mut indexed = module.clone();
+    for metadata in &functions {
+        #[allow(deprecated)]
+        indexed.update_fn_namespace(metadata.hash, FnNamespace::Global);
+    }
+    indexed.build_index();
+
+    let mut global = engine.new_global_runtime_state();
+    global.push_import("", indexed);
+
+    functions
+        .into_iter()
+        .filter_map(|metadata| {
+            let (func, _) = global.get_qualified_fn(metadata.hash, false)?;
+            let func = func.clone();
+
+            Some((metadata, func))
+        })
+        .collect()
+}
+
+/// Register `functions` in `module`, returning `false` if the hash of one of them changed.
+fn set_functions(module: &mut Module, functions: &[(FuncMetadata, RhaiFunc)]) -> bool {
+    let mut same_hashes = true;
+
+    for (metadata, func) in functions {
+        let hash = match func.get_script_fn_def() {
+            Some(fn_def) => module.set_script_fn(fn_def.clone()),
+            None => {
+                module
+                    .set_fn_raw_with_options(
+                        registration(metadata),
+                        &metadata.param_types,
+                        func.clone(),
+                    )
+                    .hash
+            }
+        };
+
+        same_hashes &= hash == metadata.hash;
+    }
+
+    same_hashes
+}
+
+/// Registration options preserving the metadata of a function.
+fn registration(metadata: &FuncMetadata) -> FuncRegistration {
+    let registration =
+        FuncRegistration::new(metadata.name.clone()).with_namespace(metadata.namespace);
+
+    #[cfg(feature = "metadata")]
+    let registration = registration
+        .with_params_info(
+            metadata
+                .params_info
+                .iter()
+                .chain(std::iter::once(&metadata.return_type)),
+        )
+        .with_comments(metadata.comments.iter());
+
+    registration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn functions_are_registered_again() {
+        let mut module = Module::new();
+        module.set_var("answer", 42 as rhai::INT);
+        module.set_native_fn("add", |a: rhai::INT, b: rhai::INT| Ok(a + b));
+
+        let mut sub_module = Module::new();
+        sub_module.set_native_fn("double", |a: rhai::INT| Ok(a * 2));
+        module.set_sub_module("math", sub_module);
+
+        let (module, dropped) = map_functions(module.into(), &|metadata, _| {
+            (metadata.name == "add").then(|| RhaiFunc::Pure {
+                func: rhai::Shared::new(|_, args| {
+                    Ok((args[0].as_int()? - args[1].as_int()?).into())
+                }),
+                has_context: false,
+                is_pure: true,
+                is_volatile: false,
+            })
+        });
+
+        let mut engine = rhai::Engine::new();
+        engine.register_static_module("plugin", module);
+
+        assert_eq!(engine.eval::<rhai::INT>("plugin::add(3, 2)").unwrap(), 1);
+        assert_eq!(
+            engine.eval::<rhai::INT>("plugin::math::double(3)").unwrap(),
+            6
+        );
+        assert_eq!(engine.eval::<rhai::INT>("plugin::answer").unwrap(), 42);
+        assert!(dropped.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! # Hashing.
+//!
+//! Rhai identifies functions with hashes computed from a seed, see [`rhai::config::hashing`].
+//! A module built by a plugin with another seed than its host registers functions
+//! that scripts will never be able to call.
+//!
+//! Each plugin exports a function receiving the seed of its host, called before its entrypoint so
+//! that the module is built with the host's hashing configuration, see [`use_host_seed`].
+//!
+//! Plugins built by third parties may not accept the seed of the host. The host then registers the
+//! functions of their module again, hashed with its own configuration, see [`rehash_module`]. The module
+//! is rebuilt in the process, and loses the items listed in `DroppedItems`.
+//!
+//! Each plugin also exports a [`Hashing`] record, compared with the host's record once the
+//! module of the plugin has been built: a plugin hashing functions differently can still fail to
+//! call functions through Rhai itself.
+
+/// Hashing configuration of a plugin or of its host.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hashing {
+    /// Is a seed set.
+    has_seed: bool,
+    /// The seed, zeroed if no seed is set.
+    seed: [u64; 4],
+    /// Hash of a probe function, identical between two builds only if they hash functions the same way.
+    fingerprint: u64,
+}
+
+impl Hashing {
+    /// Get the hashing configuration of the current build.
+    ///
+    /// Called from a plugin, this describes the plugin. Called from the host, this describes the host.
+    #[must_use]
+    pub fn current() -> Self {
+        let seed = *rhai::config::hashing::get_hashing_seed();
+
+        Self {
+            has_seed: seed.is_some(),
+            seed: seed.unwrap_or_default(),
+            fingerprint: rhai::Module::new().set_native_fn(
+                "rhai_dylib_hashing_probe",
+                || -> Result<(), Box<rhai::EvalAltResult>> { Ok(()) },
+            ),
+        }
+    }
+
+    /// The seed used to compute hashes, if any.
+    #[must_use]
+    pub const fn seed(&self) -> Option<[u64; 4]> {
+        if self.has_seed {
+            Some(self.seed)
+        } else {
+            None
+        }
+    }
+
+    /// Hash of a probe function, identical between two builds only if they hash functions the same way.
+    #[must_use]
+    pub const fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Does `other` hash functions the same way as `self`.
+    #[must_use]
+    pub const fn matches(&self, other: &Self) -> bool {
+        self.fingerprint == other.fingerprint
+    }
+}
+
+/// Make the current build hash functions with the seed of the host.
+///
+/// This must be called before any hash is computed, thus before building the module of the plugin.
+/// It has no effect if a seed has already been set.
+pub fn use_host_seed(seed: &[u64; 4]) {
+    let _ = rhai::config::hashing::set_hashing_seed(Some(*seed));
+}
+
+/// Register again every function of a module built by a plugin and of its sub-modules, so that they
+/// are hashed like the functions of the host, whatever the hashing configuration of the plugin.
+///
+/// Called by the host. If the hashes change, the module is rebuilt: its type iterators and private
+/// native functions cannot be carried over and are counted in the returned [`DroppedItems`], and the
+/// names of its custom types are only kept with the `metadata` feature.
+#[must_use]
+pub fn rehash_module(
+    module: rhai::Shared<rhai::Module>,
+) -> (rhai::Shared<rhai::Module>, DroppedItems) {
+    super::functions::map_functions(module, &|_, _| None)
+}
+
+/// Items of a module left out when it was rebuilt to hash its functions like the host, see [`rehash_module`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DroppedItems {
+    /// Number of type iterators left out, with those of sub-modules.
+    pub type_iterators: usize,
+    /// Number of private native functions left out, with those of sub-modules.
+    pub private_functions: usize,
+}
+
+impl DroppedItems {
+    /// Was nothing left out.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.type_iterators == 0 && self.private_functions == 0
+    }
+}
+
+impl std::fmt::Display for DroppedItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "module rebuilt to hash its functions like the host, without its {} type iterator(s) and {} private function(s)",
+            self.type_iterators, self.private_functions
+        )
+    }
+}
+
+/// Reported when a plugin does not hash functions the same way as its host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashingMismatch {
+    /// Seed of the host.
+    pub host: Option<[u64; 4]>,
+    /// Seed of the plugin.
+    pub plugin: Option<[u64; 4]>,
+    /// Fingerprint of the host, see [`Hashing::fingerprint`].
+    pub host_fingerprint: u64,
+    /// Fingerprint of the plugin, see [`Hashing::fingerprint`].
+    pub plugin_fingerprint: u64,
+}
+
+impl HashingMismatch {
+    /// Describe the differences between the hashing configurations of `host` and `plugin`.
+    #[must_use]
+    pub const fn new(host: &Hashing, plugin: &Hashing) -> Self {
+        Self {
+            host: host.seed(),
+            plugin: plugin.seed(),
+            host_fingerprint: host.fingerprint,
+            plugin_fingerprint: plugin.fingerprint,
+        }
+    }
+}
+
+impl std::fmt::Display for HashingMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seed = |seed: Option<[u64; 4]>| {
+            seed.map_or_else(|| "none".to_string(), |seed| format!("{seed:?}"))
+        };
+
+        write!(
+            f,
+            "hashing seed mismatch (host: {}, plugin: {}; fingerprints: host {:#018x}, plugin {:#018x})",
+            seed(self.host),
+            seed(self.plugin),
+            self.host_fingerprint,
+            self.plugin_fingerprint
+        )?;
+
+        if self.host.is_none() {
+            write!(
+                f,
+                ", the host must set a seed with `rhai::config::hashing::set_hashing_seed`"
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for HashingMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_matches_itself() {
+        let current = Hashing::current();
+
+        assert!(current.matches(&Hashing::current()));
+        assert_eq!(current.seed(), *rhai::config::hashing::get_hashing_seed());
+    }
+
+    #[test]
+    fn mismatch_names_both_seeds() {
+        let error = HashingMismatch {
+            host: Some([1, 2, 3, 4]),
+            plugin: None,
+            host_fingerprint: 1,
+            plugin_fingerprint: 2,
+        };
+
+        assert!(error
+            .to_string()
+            .contains("host: [1, 2, 3, 4], plugin: none"));
+        assert!(error
+            .to_string()
+            .contains("host 0x0000000000000001, plugin 0x0000000000000002"));
+    }
+}
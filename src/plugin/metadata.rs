@@ -0,0 +1,200 @@
+//! # Metadata.
+//!
+//! Each plugin exports a [`Metadata`] record describing itself: its name, version, description and authors.
+//! The loader reads it when loading the plugin, so that the host can tell which plugin it loaded.
+//! Plugins without a record are named after the file stem of their library.
+//!
+//! By default, [`declare_plugin`](crate::declare_plugin) fills the record with the package
+//! information of the plugin crate.
+
+/// A string shared between a plugin and its host.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct RawStr {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl RawStr {
+    const fn new(value: &'static str) -> Self {
+        Self {
+            ptr: value.as_ptr(),
+            len: value.len(),
+        }
+    }
+
+    /// Read the string, replacing invalid UTF-8 sequences.
+    fn read(&self) -> std::borrow::Cow<'_, str> {
+        if self.ptr.is_null() || self.len == 0 {
+            return std::borrow::Cow::Borrowed("");
+        }
+
+        // SAFETY: raw strings created by `RawStr::new` point to static strings. Records exported by a
+        // plugin, possibly written in another language, must point to `len` bytes that stay valid while
+        // the plugin is loaded: the loader copies them before the plugin can be unloaded.
+        let bytes = unsafe { std::slice::from_raw_parts(self.ptr, self.len) };
+        String::from_utf8_lossy(bytes)
+    }
+}
+
+/// Description of a plugin.
+///
+/// The strings of this record point into the memory of the plugin: they must be copied
+/// before the plugin is unloaded. Invalid UTF-8 sequences are replaced when they are read.
+///
+/// Plugins using the C ABI can export this record with the following layout, see [`super::ffi`]:
+///
+/// ```c
+/// typedef struct { const uint8_t *ptr; size_t len; } FfiStr;
+///
+/// typedef struct {
+///     FfiStr name;
+///     FfiStr version;
+///     FfiStr description;
+///     FfiStr authors; // Separated by `:`.
+/// } Metadata;
+///
+/// Metadata module_metadata(void) {
+///     return (Metadata) { .name = { (const uint8_t *) "my_plugin", 9 } };
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    name: RawStr,
+    version: RawStr,
+    description: RawStr,
+    /// Authors separated by `:`, like the `CARGO_PKG_AUTHORS` environment variable.
+    authors: RawStr,
+}
+
+impl Metadata {
+    /// Create a record builder, starting from the package information of a crate.
+    ///
+    /// `authors` are separated by `:`, like the `CARGO_PKG_AUTHORS` environment variable.
+    #[must_use]
+    pub const fn builder(
+        name: &'static str,
+        version: &'static str,
+        description: &'static str,
+        authors: &'static str,
+    ) -> MetadataBuilder {
+        MetadataBuilder(Self {
+            name: RawStr::new(name),
+            version: RawStr::new(version),
+            description: RawStr::new(description),
+            authors: RawStr::new(authors),
+        })
+    }
+
+    /// Name of the plugin.
+    #[must_use]
+    pub fn name(&self) -> std::borrow::Cow<'_, str> {
+        self.name.read()
+    }
+
+    /// Version of the plugin.
+    #[must_use]
+    pub fn version(&self) -> std::borrow::Cow<'_, str> {
+        self.version.read()
+    }
+
+    /// Description of the plugin.
+    #[must_use]
+    pub fn description(&self) -> std::borrow::Cow<'_, str> {
+        self.description.read()
+    }
+
+    /// Authors of the plugin.
+    #[must_use]
+    pub fn authors(&self) -> Vec<String> {
+        self.authors
+            .read()
+            .split(':')
+            .filter(|author| !author.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+/// Builder of a [`Metadata`] record, used by [`declare_plugin`](crate::declare_plugin).
+#[derive(Debug, Clone, Copy)]
+pub struct MetadataBuilder(Metadata);
+
+impl MetadataBuilder {
+    /// Set the name of the plugin.
+    #[must_use]
+    pub const fn name(mut self, name: &'static str) -> Self {
+        self.0.name = RawStr::new(name);
+        self
+    }
+
+    /// Set the version of the plugin.
+    #[must_use]
+    pub const fn version(mut self, version: &'static str) -> Self {
+        self.0.version = RawStr::new(version);
+        self
+    }
+
+    /// Set the description of the plugin.
+    #[must_use]
+    pub const fn description(mut self, description: &'static str) -> Self {
+        self.0.description = RawStr::new(description);
+        self
+    }
+
+    /// Set the authors of the plugin, separated by `:`.
+    #[must_use]
+    pub const fn authors(mut self, authors: &'static str) -> Self {
+        self.0.authors = RawStr::new(authors);
+        self
+    }
+
+    /// Build the record.
+    #[must_use]
+    pub const fn build(self) -> Metadata {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_overrides_package_information() {
+        let metadata = Metadata::builder("package", "0.1.0", "A package", "Jane Doe:John Doe")
+            .name("plugin")
+            .version("1.0.0")
+            .build();
+
+        assert_eq!(metadata.name(), "plugin");
+        assert_eq!(metadata.version(), "1.0.0");
+        assert_eq!(metadata.description(), "A package");
+        assert_eq!(metadata.authors(), vec!["Jane Doe", "John Doe"]);
+    }
+
+    #[test]
+    fn empty_authors() {
+        let metadata = Metadata::builder("package", "0.1.0", "", "").build();
+
+        assert!(metadata.authors().is_empty());
+    }
+
+    #[test]
+    fn foreign_strings() {
+        let invalid = b"plug\xffin";
+        let mut metadata = Metadata::builder("", "", "", "").build();
+        metadata.name = RawStr {
+            ptr: invalid.as_ptr(),
+            len: invalid.len(),
+        };
+        metadata.version = RawStr {
+            ptr: std::ptr::null(),
+            len: 5,
+        };
+
+        assert_eq!(metadata.name(), "plug\u{fffd}in");
+        assert_eq!(metadata.version(), "");
+    }
+}
@@ -0,0 +1,490 @@
+//! # Plugin.
+//!
+//! Items exported by a plugin: the entrypoint building its module, and the functions letting a host
+//! check it before and after calling its entrypoint.
+//!
+//! The [`declare_plugin`](crate::declare_plugin) macro generates all of them.
+//!
+//! ```rust,ignore
+//! #[rhai_dylib::rhai::plugin::export_module]
+//! mod my_api {
+//!     pub fn get_num() -> rhai_dylib::rhai::INT {
+//!         3
+//!     }
+//! }
+//!
+//! rhai_dylib::declare_plugin!(my_api);
+//! ```
+//!
+//! Which expands to the [`MODULE_ENTRYPOINT_V2`] entrypoint catching panics, along with those of the
+//! functions of the module if the host asks for it, a metadata record describing the plugin, a compatibility record describing the environment the plugin was built in,
+//! a record of the type ids of core Rhai types, a function adopting the hashing seed of the host
+//! and a record describing how the plugin hashes functions:
+//!
+//! ```rust,ignore
+//! #[no_mangle]
+//! pub extern "C" fn module_metadata() -> rhai_dylib::plugin::metadata::Metadata {
+//!     rhai_dylib::plugin::metadata::Metadata::builder(
+//!         env!("CARGO_PKG_NAME"),
+//!         env!("CARGO_PKG_VERSION"),
+//!         env!("CARGO_PKG_DESCRIPTION"),
+//!         env!("CARGO_PKG_AUTHORS"),
+//!     )
+//!     .build()
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn module_compatibility() -> *const rhai_dylib::plugin::compatibility::Compatibility {
+//!     rhai_dylib::plugin::compatibility::Compatibility::record()
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn module_type_ids() -> rhai_dylib::plugin::type_ids::TypeIds {
+//!     rhai_dylib::plugin::type_ids::TypeIds::current()
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn module_set_hashing_seed(seed: &[u64; 4]) {
+//!     rhai_dylib::plugin::hashing::use_host_seed(seed);
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn module_hashing() -> rhai_dylib::plugin::hashing::Hashing {
+//!     rhai_dylib::plugin::hashing::Hashing::current()
+//! }
+//!
+//! #[allow(improper_ctypes_definitions)]
+//! #[no_mangle]
+//! pub extern "C" fn module_entrypoint_v2(
+//!     context: &rhai_dylib::plugin::context::HostContext,
+//! ) -> Result<rhai_dylib::rhai::Shared<rhai_dylib::rhai::Module>, String> {
+//!     rhai_dylib::plugin::catch_panic(|| -> Result<_, String> {
+//!         let module = rhai_dylib::plugin::IntoModuleResult::into_module_result(
+//!             rhai_dylib::rhai::exported_module!(my_api),
+//!         )?;
+//!
+//!         if context.is_panic_catching_enabled() {
+//!             Ok(rhai_dylib::plugin::panics::catch_function_panics(&module_metadata().name(), module))
+//!         } else {
+//!             Ok(module)
+//!         }
+//!     })
+//!     .and_then(std::convert::identity)
+//! }
+//! ```
+
+/// Build environment record compared by the host before loading a plugin.
+pub mod compatibility;
+/// Context handed by the host to the entrypoint of a plugin.
+pub mod context;
+/// C-compatible plugin interface, independent of the compiler and Rhai build of the plugin.
+pub mod ffi;
+/// Rebuilding the functions of a module.
+pub(crate) mod functions;
+/// Hashing configuration record compared by the host after loading a plugin.
+pub mod hashing;
+/// Metadata record describing a plugin.
+pub mod metadata;
+/// Catching panics of the functions of a plugin.
+pub mod panics;
+/// Named module constructors exported by a plugin.
+pub mod registry;
+/// Type ids record compared by the host before loading a plugin.
+pub mod type_ids;
+
+/// Entrypoint prototype for a Rhai module "constructor".
+#[allow(improper_ctypes_definitions)]
+pub type Entrypoint = extern "C" fn() -> rhai::Shared<rhai::Module>;
+/// The name of the function that will be called to build the module of a plugin.
+pub const MODULE_ENTRYPOINT: &str = "module_entrypoint";
+
+/// Entrypoint prototype for a Rhai module "constructor" receiving the context of the host.
+///
+/// The entrypoint returns an error message instead of unwinding into the host if it panics, see [`catch_panic`].
+#[allow(improper_ctypes_definitions)]
+pub type EntrypointV2 =
+    extern "C" fn(context: &context::HostContext) -> Result<rhai::Shared<rhai::Module>, String>;
+/// The name of the function that will be called to build the module of a plugin, with the context of the host.
+///
+/// Preferred over [`MODULE_ENTRYPOINT`] when a plugin exports both.
+pub const MODULE_ENTRYPOINT_V2: &str = "module_entrypoint_v2";
+
+/// Prototype of the function returning the names of the modules of the [`registry::ModuleRegistry`] of a plugin.
+///
+/// The function returns an error message instead of unwinding into the host if it panics, see [`catch_panic`].
+#[allow(improper_ctypes_definitions)]
+pub type ModuleNamesFn = extern "C" fn() -> Result<Vec<String>, String>;
+/// The name of the function returning the names of the modules of the [`registry::ModuleRegistry`] of a plugin.
+pub const MODULE_NAMES: &str = "module_names";
+
+/// Entrypoint prototype building one of the modules of the [`registry::ModuleRegistry`] of a plugin, by name.
+///
+/// The entrypoint returns an error message instead of unwinding into the host if it panics, see [`catch_panic`].
+#[allow(improper_ctypes_definitions)]
+pub type NamedEntrypoint = extern "C" fn(
+    name: &str,
+    context: &context::HostContext,
+) -> Result<rhai::Shared<rhai::Module>, String>;
+/// The name of the function building one of the modules of the [`registry::ModuleRegistry`] of a plugin.
+pub const MODULE_NAMED_ENTRYPOINT: &str = "module_named_entrypoint";
+
+/// The name of the entrypoint of a plugin using the C ABI, following the [`ffi::FfiEntrypoint`] prototype.
+///
+/// Preferred over [`MODULE_ENTRYPOINT_V2`] and [`MODULE_ENTRYPOINT`] when a plugin exports several of them.
+pub const MODULE_FFI_ENTRYPOINT: &str = "module_ffi_entrypoint";
+
+/// Prototype of the function letting a plugin register items in the [`rhai::Engine`] of the host.
+///
+/// The function returns an error message instead of unwinding into the host if it panics, see [`catch_panic`].
+#[allow(improper_ctypes_definitions)]
+pub type EngineEntrypoint =
+    extern "C" fn(engine: &mut rhai::Engine, context: &context::HostContext) -> Result<(), String>;
+/// The name of the function letting a plugin register items in the [`rhai::Engine`] of the host.
+pub const MODULE_ENGINE_ENTRYPOINT: &str = "module_engine_entrypoint";
+
+/// The name of the function returning the [`metadata::Metadata`] record of a plugin.
+pub const MODULE_METADATA: &str = "module_metadata";
+
+/// Prototype of the function returning the [`metadata::Metadata`] record of a plugin.
+pub type MetadataFn = extern "C" fn() -> metadata::Metadata;
+
+/// The name of the function returning the [`compatibility::Compatibility`] record of a plugin.
+pub const MODULE_COMPATIBILITY: &str = "module_compatibility";
+
+/// Prototype of the function returning the [`compatibility::Compatibility`] record of a plugin.
+///
+/// The record is returned by pointer, so that a host reads its version before anything else.
+pub type CompatibilityFn = extern "C" fn() -> *const compatibility::Compatibility;
+
+/// The name of the function returning the [`hashing::Hashing`] record of a plugin.
+pub const MODULE_HASHING: &str = "module_hashing";
+
+/// Prototype of the function returning the [`hashing::Hashing`] record of a plugin.
+pub type HashingFn = extern "C" fn() -> hashing::Hashing;
+
+/// The name of the function receiving the hashing seed of the host, see [`hashing::use_host_seed`].
+pub const MODULE_SET_HASHING_SEED: &str = "module_set_hashing_seed";
+
+/// Prototype of the function receiving the hashing seed of the host.
+pub type SetHashingSeedFn = extern "C" fn(seed: &[u64; 4]);
+
+/// The name of the function returning the [`type_ids::TypeIds`] record of a plugin.
+pub const MODULE_TYPE_IDS: &str = "module_type_ids";
+
+/// Prototype of the function returning the [`type_ids::TypeIds`] record of a plugin.
+pub type TypeIdsFn = extern "C" fn() -> type_ids::TypeIds;
+
+/// Call `f`, turning a panic into an error message.
+///
+/// Unwinding out of an `extern "C"` function aborts the process, and the host cannot catch panics
+/// raised by the standard library of a plugin. Entrypoints of plugins must catch their own panics
+/// with this function, which [`declare_plugin`](crate::declare_plugin) does.
+///
+/// Panics cannot be caught if the plugin is built with `panic = "abort"`.
+///
+/// # Errors
+///
+/// Returns the message of the panic if `f` panicked.
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .map_err(|payload| format!("plugin panicked: {}", panic_message(payload.as_ref())))
+}
+
+/// Value returned by a function building the module of a plugin, see [`declare_plugin`](crate::declare_plugin).
+///
+/// Implemented for modules and for results holding a module or an error describing why the plugin
+/// failed to initialize, which the host receives as a `LoadError::Plugin` error, see `crate::loader::libloading`.
+pub trait IntoModuleResult {
+    /// Convert the value to the result returned by the [`MODULE_ENTRYPOINT_V2`] entrypoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns the message of the error if the plugin failed to build its module.
+    fn into_module_result(self) -> Result<rhai::Shared<rhai::Module>, String>;
+}
+
+impl IntoModuleResult for rhai::Module {
+    fn into_module_result(self) -> Result<rhai::Shared<rhai::Module>, String> {
+        Ok(self.into())
+    }
+}
+
+impl IntoModuleResult for rhai::Shared<rhai::Module> {
+    fn into_module_result(self) -> Result<rhai::Shared<rhai::Module>, String> {
+        Ok(self)
+    }
+}
+
+impl<M, E> IntoModuleResult for Result<M, E>
+where
+    M: Into<rhai::Shared<rhai::Module>>,
+    E: std::fmt::Display,
+{
+    fn into_module_result(self) -> Result<rhai::Shared<rhai::Module>, String> {
+        self.map(Into::into).map_err(|error| error.to_string())
+    }
+}
+
+/// Message of a panic, from its payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Declare a plugin: generate its entrypoint and every function read by the loader.
+///
+/// The module of the plugin is either a module exported with Rhai's
+/// [`export_module`](rhai::plugin::export_module) macro, or built by a function returning
+/// a [`rhai::Module`] or a [`rhai::Shared<rhai::Module>`]. Using the `entrypoint_with_context` key,
+/// the function receives the [`context::HostContext`] of the host.
+///
+/// A function failing to build the module returns a [`Result`] instead, see [`IntoModuleResult`]:
+/// the loader fails with the message of the error.
+///
+/// Using the `registry` key instead, the plugin exports several modules built by a function returning
+/// a [`registry::ModuleRegistry`].
+///
+/// The `engine` key, right after the module, sets a function registering items in the [`rhai::Engine`]
+/// of the host, such as types, operators or custom syntax.
+///
+/// The metadata of the plugin defaults to the package information of the crate
+/// and can be overridden with the `name`, `version`, `description` and `authors` keys.
+///
+/// ```rust,ignore
+/// // From an exported module.
+/// rhai_dylib::declare_plugin!(my_api);
+///
+/// // From a function, with custom metadata.
+/// fn build_module() -> rhai_dylib::rhai::Module {
+///     // ...
+/// }
+///
+/// rhai_dylib::declare_plugin!(
+///     entrypoint = build_module,
+///     name = "my_plugin",
+///     version = "1.0.0",
+///     description = "My plugin",
+///     authors = ["Jane Doe", "John Doe"],
+/// );
+///
+/// // From a function receiving the context of the host.
+/// fn build_module(context: &rhai_dylib::plugin::context::HostContext) -> rhai_dylib::rhai::Module {
+///     // ...
+/// }
+///
+/// rhai_dylib::declare_plugin!(entrypoint_with_context = build_module);
+///
+/// // From a function that can fail.
+/// fn build_module(context: &rhai_dylib::plugin::context::HostContext) -> Result<rhai_dylib::rhai::Module, String> {
+///     let path = context.config().get("path").ok_or("missing `path` configuration")?;
+///     // ...
+/// }
+///
+/// rhai_dylib::declare_plugin!(entrypoint_with_context = build_module);
+///
+/// // Several modules.
+/// fn build_registry() -> rhai_dylib::plugin::registry::ModuleRegistry {
+///     let mut registry = rhai_dylib::plugin::registry::ModuleRegistry::new();
+///     registry.register("fs", |_| rhai_dylib::rhai::exported_module!(fs_api));
+///     registry
+/// }
+///
+/// rhai_dylib::declare_plugin!(registry = build_registry);
+///
+/// // With a function registering items in the engine of the host.
+/// fn register(engine: &mut rhai_dylib::rhai::Engine, context: &rhai_dylib::plugin::context::HostContext) {
+///     engine.register_custom_operator("plus", 160).unwrap();
+/// }
+///
+/// rhai_dylib::declare_plugin!(my_api, engine = register);
+/// ```
+///
+/// The generated functions can only be declared once per library.
+#[macro_export]
+macro_rules! declare_plugin {
+    (@metadata $builder:expr;) => {
+        $builder
+    };
+    (@metadata $builder:expr; authors = [$($author:literal),* $(,)?] $(, $($rest:tt)*)?) => {
+        $crate::declare_plugin!(@metadata $builder.authors(concat!($($author, ":"),*)); $($($rest)*)?)
+    };
+    (@metadata $builder:expr; $key:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::declare_plugin!(@metadata $builder.$key($value); $($($rest)*)?)
+    };
+    (@records $($metadata:tt)*) => {
+        // Function names must match the `MODULE_*` constants of `rhai_dylib::plugin`.
+
+        #[no_mangle]
+        pub extern "C" fn module_metadata() -> $crate::plugin::metadata::Metadata {
+            $crate::declare_plugin!(
+                @metadata $crate::plugin::metadata::Metadata::builder(
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION"),
+                    env!("CARGO_PKG_DESCRIPTION"),
+                    env!("CARGO_PKG_AUTHORS"),
+                );
+                $($metadata)*
+            )
+            .build()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn module_compatibility() -> *const $crate::plugin::compatibility::Compatibility {
+            $crate::plugin::compatibility::Compatibility::record()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn module_type_ids() -> $crate::plugin::type_ids::TypeIds {
+            $crate::plugin::type_ids::TypeIds::current()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn module_set_hashing_seed(seed: &[u64; 4]) {
+            $crate::plugin::hashing::use_host_seed(seed);
+        }
+
+        #[no_mangle]
+        pub extern "C" fn module_hashing() -> $crate::plugin::hashing::Hashing {
+            $crate::plugin::hashing::Hashing::current()
+        }
+
+        // Fails to compile if the functions drift from the prototypes expected by the loader.
+        const _: $crate::plugin::MetadataFn = module_metadata;
+        const _: $crate::plugin::CompatibilityFn = module_compatibility;
+        const _: $crate::plugin::TypeIdsFn = module_type_ids;
+        const _: $crate::plugin::SetHashingSeedFn = module_set_hashing_seed;
+        const _: $crate::plugin::HashingFn = module_hashing;
+    };
+    (@guard $module:expr, $context:expr) => {
+        if $context.is_panic_catching_enabled() {
+            $crate::plugin::panics::catch_function_panics(&module_metadata().name(), $module)
+        } else {
+            $module
+        }
+    };
+    (@registry $registry:expr) => {
+        #[allow(improper_ctypes_definitions)]
+        #[no_mangle]
+        pub extern "C" fn module_names() -> Result<Vec<String>, String> {
+            $crate::plugin::catch_panic(|| ($registry)().names().map(ToString::to_string).collect())
+        }
+
+        #[allow(improper_ctypes_definitions)]
+        #[no_mangle]
+        pub extern "C" fn module_named_entrypoint(
+            name: &str,
+            context: &$crate::plugin::context::HostContext,
+        ) -> Result<$crate::rhai::Shared<$crate::rhai::Module>, String> {
+            $crate::plugin::catch_panic(|| -> Result<_, String> {
+                let module = ($registry)().build(name, context)?;
+
+                Ok($crate::declare_plugin!(@guard module, context))
+            })
+            .and_then(std::convert::identity)
+        }
+
+        // Fails to compile if the functions drift from the prototypes expected by the loader.
+        const _: $crate::plugin::ModuleNamesFn = module_names;
+        const _: $crate::plugin::NamedEntrypoint = module_named_entrypoint;
+    };
+    (@engine $engine:expr) => {
+        #[allow(improper_ctypes_definitions)]
+        #[no_mangle]
+        pub extern "C" fn module_engine_entrypoint(
+            engine: &mut $crate::rhai::Engine,
+            context: &$crate::plugin::context::HostContext,
+        ) -> Result<(), String> {
+            $crate::plugin::catch_panic(|| ($engine)(engine, context))
+        }
+
+        // Fails to compile if the entrypoint drifts from the prototype expected by the loader.
+        const _: $crate::plugin::EngineEntrypoint = module_engine_entrypoint;
+    };
+    (entrypoint = $entrypoint:expr, engine = $engine:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(entrypoint = $entrypoint $(, $($metadata)*)?);
+        $crate::declare_plugin!(@engine $engine);
+    };
+    (entrypoint_with_context = $entrypoint:expr, engine = $engine:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(entrypoint_with_context = $entrypoint $(, $($metadata)*)?);
+        $crate::declare_plugin!(@engine $engine);
+    };
+    (entrypoint = $entrypoint:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(
+            entrypoint_with_context = |_: &$crate::plugin::context::HostContext| ($entrypoint)()
+            $(, $($metadata)*)?
+        );
+    };
+    (entrypoint_with_context = $entrypoint:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(@records $($($metadata)*)?);
+
+        #[allow(improper_ctypes_definitions)]
+        #[no_mangle]
+        pub extern "C" fn module_entrypoint_v2(
+            context: &$crate::plugin::context::HostContext,
+        ) -> Result<$crate::rhai::Shared<$crate::rhai::Module>, String> {
+            $crate::plugin::catch_panic(|| -> Result<_, String> {
+                let module =
+                    $crate::plugin::IntoModuleResult::into_module_result(($entrypoint)(context))?;
+
+                Ok($crate::declare_plugin!(@guard module, context))
+            })
+            .and_then(std::convert::identity)
+        }
+
+        // Fails to compile if the entrypoint drifts from the prototype expected by the loader.
+        const _: $crate::plugin::EntrypointV2 = module_entrypoint_v2;
+    };
+    (registry = $registry:expr, engine = $engine:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(registry = $registry $(, $($metadata)*)?);
+        $crate::declare_plugin!(@engine $engine);
+    };
+    (registry = $registry:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(@records $($($metadata)*)?);
+        $crate::declare_plugin!(@registry $registry);
+    };
+    ($module:path, engine = $engine:expr $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!($module $(, $($metadata)*)?);
+        $crate::declare_plugin!(@engine $engine);
+    };
+    ($module:path $(, $($metadata:tt)*)?) => {
+        $crate::declare_plugin!(
+            entrypoint = || $crate::rhai::exported_module!($module)
+            $(, $($metadata)*)?
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catch_panic_returns_message() {
+        assert_eq!(catch_panic(|| 42), Ok(42));
+        assert_eq!(
+            catch_panic(|| panic!("static message")),
+            Err::<(), _>("plugin panicked: static message".to_string())
+        );
+        assert_eq!(
+            catch_panic(|| panic!("formatted {}", "message")),
+            Err::<(), _>("plugin panicked: formatted message".to_string())
+        );
+    }
+
+    #[test]
+    fn into_module_result() {
+        assert!(rhai::Module::new().into_module_result().is_ok());
+        assert!(Ok::<_, String>(rhai::Shared::new(rhai::Module::new()))
+            .into_module_result()
+            .is_ok());
+        assert_eq!(
+            Err::<rhai::Module, _>("missing configuration")
+                .into_module_result()
+                .unwrap_err(),
+            "missing configuration"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! # Function panics.
+//!
+//! A native function of a plugin panicking during a script call unwinds into the host. When the host
+//! enables panic catching for a plugin, see [`HostContext::is_panic_catching_enabled`](super::context::HostContext::is_panic_catching_enabled),
+//! [`declare_plugin`](crate::declare_plugin) wraps every function of its module with [`catch_function_panics`],
+//! turning panics into [`rhai::EvalAltResult::ErrorRuntime`] errors.
+//!
+//! Wrapping adds a [`std::panic::catch_unwind`] call to each function call, which is why it is opt-in.
+
+use super::functions::map_functions;
+use rhai::plugin::{PluginFunc, RhaiFunc};
+use rhai::{Module, NativeCallContext};
+
+/// Wrap every public native function of `module` and of its sub-modules, so that a panic in a function
+/// is returned as an [`rhai::EvalAltResult::ErrorRuntime`] error naming `plugin` and the function.
+///
+/// Functions must be wrapped in the plugin: the host cannot catch panics raised by the standard library of a plugin.
+/// Private functions, script-defined functions and type iterators are left as is.
+#[must_use]
+pub fn catch_function_panics(plugin: &str, module: rhai::Shared<Module>) -> rhai::Shared<Module> {
+    let plugin = rhai::ImmutableString::from(plugin);
+
+    // The module was built by the plugin, its functions keep their hashes and nothing is dropped.
+    map_functions(module, &|metadata, func| {
+        wrap_function(&plugin, &metadata.name, func)
+    })
+    .0
+}
+
+fn wrap_function(plugin: &rhai::ImmutableString, name: &str, func: &RhaiFunc) -> Option<RhaiFunc> {
+    let guard = FunctionGuard {
+        plugin: plugin.clone(),
+        function: name.into(),
+    };
+
+    match func.clone() {
+        RhaiFunc::Pure {
+            func,
+            has_context,
+            is_pure,
+            is_volatile,
+        } => Some(RhaiFunc::Pure {
+            func: rhai::Shared::new(move |context, args| {
+                guard.call(context, |context| func(context, args))
+            }),
+            has_context,
+            is_pure,
+            is_volatile,
+        }),
+        RhaiFunc::Method {
+            func,
+            has_context,
+            is_pure,
+            is_volatile,
+        } => Some(RhaiFunc::Method {
+            func: rhai::Shared::new(move |context, args| {
+                guard.call(context, |context| func(context, args))
+            }),
+            has_context,
+            is_pure,
+            is_volatile,
+        }),
+        func @ RhaiFunc::Plugin { .. } => Some(RhaiFunc::Plugin {
+            func: rhai::Shared::new(PluginFunctionGuard { guard, func }),
+        }),
+        _ => None,
+    }
+}
+
+/// Names of a wrapped function, used to tag its panics.
+struct FunctionGuard {
+    plugin: rhai::ImmutableString,
+    function: rhai::ImmutableString,
+}
+
+impl FunctionGuard {
+    fn call(
+        &self,
+        context: Option<NativeCallContext>,
+        f: impl FnOnce(Option<NativeCallContext>) -> rhai::plugin::RhaiResult,
+    ) -> rhai::plugin::RhaiResult {
+        let position = context
+            .as_ref()
+            .map_or(rhai::Position::NONE, NativeCallContext::call_position);
+
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(context))).unwrap_or_else(
+            |payload| {
+                Err(rhai::EvalAltResult::ErrorRuntime(
+                    format!(
+                        "plugin `{}` panicked in function `{}`: {}",
+                        self.plugin,
+                        self.function,
+                        super::panic_message(payload.as_ref())
+                    )
+                    .into(),
+                    position,
+                )
+                .into())
+            },
+        )
+    }
+}
+
+/// Functions generated by Rhai's plugin macros are trait objects, wrapped in another one.
+struct PluginFunctionGuard {
+    guard: FunctionGuard,
+    /// Always a [`RhaiFunc::Plugin`].
+    func: RhaiFunc,
+}
+
+impl PluginFunctionGuard {
+    fn inner(&self) -> &dyn PluginFunc {
+        &**self
+            .func
+            .get_plugin_fn()
+            .expect("only plugin functions are guarded")
+    }
+}
+
+impl PluginFunc for PluginFunctionGuard {
+    fn call(
+        &self,
+        context: Option<NativeCallContext>,
+        args: &mut [&mut rhai::Dynamic],
+    ) -> rhai::plugin::RhaiResult {
+        self.guard
+            .call(context, |context| self.inner().call(context, args))
+    }
+
+    fn is_method_call(&self) -> bool {
+        self.inner().is_method_call()
+    }
+
+    fn has_context(&self) -> bool {
+        self.inner().has_context()
+    }
+
+    fn is_pure(&self) -> bool {
+        self.inner().is_pure()
+    }
+
+    fn is_volatile(&self) -> bool {
+        self.inner().is_volatile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rhai::export_module]
+    mod api {
+        pub fn exported() -> rhai::INT {
+            panic!("exported function panicked")
+        }
+    }
+
+    fn error(engine: &rhai::Engine, script: &str) -> String {
+        match *engine.eval::<rhai::INT>(script).unwrap_err() {
+            rhai::EvalAltResult::ErrorRuntime(message, _) => message.to_string(),
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn panics_become_runtime_errors() {
+        let mut module = Module::new();
+        module.set_native_fn("answer", || Ok(42 as rhai::INT));
+        module.set_native_fn("fail", || -> Result<rhai::INT, _> {
+            panic!("native function panicked")
+        });
+        module.set_sub_module("api", rhai::exported_module!(api));
+
+        let module = catch_function_panics("my_plugin", module.into());
+
+        let mut engine = rhai::Engine::new();
+        engine.register_static_module("plugin", module);
+
+        assert_eq!(engine.eval::<rhai::INT>("plugin::answer()").unwrap(), 42);
+        assert_eq!(
+            error(&engine, "plugin::fail()"),
+            "plugin `my_plugin` panicked in function `fail`: native function panicked"
+        );
+        assert_eq!(
+            error(&engine, "plugin::api::exported()"),
+            "plugin `my_plugin` panicked in function `exported`: exported function panicked"
+        );
+    }
+}
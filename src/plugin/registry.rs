@@ -0,0 +1,124 @@
+//! # Module registry.
+//!
+//! A plugin can export several modules, each built by a constructor registered under a name in a
+//! [`ModuleRegistry`]. The host builds all of them, or only the ones it imports, see
+//! [`MODULE_NAMES`](super::MODULE_NAMES) and [`MODULE_NAMED_ENTRYPOINT`](super::MODULE_NAMED_ENTRYPOINT).
+//!
+//! ```rust,ignore
+//! fn build_registry() -> ModuleRegistry {
+//!     let mut registry = ModuleRegistry::new();
+//!
+//!     registry
+//!         .register("fs", |_| rhai_dylib::rhai::exported_module!(fs_api))
+//!         .register("net", build_net_module);
+//!
+//!     registry
+//! }
+//!
+//! rhai_dylib::declare_plugin!(registry = build_registry);
+//! ```
+
+use super::context::HostContext;
+use super::IntoModuleResult;
+
+/// Constructor of a module of a [`ModuleRegistry`].
+type Constructor = Box<dyn Fn(&HostContext) -> Result<rhai::Shared<rhai::Module>, String>>;
+
+/// Named module constructors exported by a plugin.
+#[derive(Default)]
+pub struct ModuleRegistry {
+    constructors: std::collections::BTreeMap<String, Constructor>,
+}
+
+impl std::fmt::Debug for ModuleRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+impl ModuleRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the constructor of the module named `name`, replacing any constructor registered with the same name.
+    ///
+    /// The constructor returns a module, or a result holding a module, see [`IntoModuleResult`].
+    pub fn register<R: IntoModuleResult>(
+        &mut self,
+        name: impl Into<String>,
+        constructor: impl Fn(&HostContext) -> R + 'static,
+    ) -> &mut Self {
+        self.constructors.insert(
+            name.into(),
+            Box::new(move |context| constructor(context).into_module_result()),
+        );
+        self
+    }
+
+    /// Names of the modules of the registry, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(String::as_str)
+    }
+
+    /// Is a module registered under this name?
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.constructors.contains_key(name)
+    }
+
+    /// Build the module named `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no module is registered under this name or if its constructor failed.
+    pub fn build(
+        &self,
+        name: &str,
+        context: &HostContext,
+    ) -> Result<rhai::Shared<rhai::Module>, String> {
+        let constructor = self
+            .constructors
+            .get(name)
+            .ok_or_else(|| format!("no module named `{name}`"))?;
+
+        constructor(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let mut registry = ModuleRegistry::new();
+
+        registry.register("json", |_| rhai::Module::new()).register(
+            "fs",
+            |context: &HostContext| {
+                if context.config().contains_key("root") {
+                    Ok(rhai::Module::new())
+                } else {
+                    Err("missing `root` configuration")
+                }
+            },
+        );
+
+        let context = HostContext::default();
+
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["fs", "json"]);
+        assert!(registry.contains("json"));
+        assert!(registry.build("json", &context).is_ok());
+        assert_eq!(
+            registry.build("fs", &context).unwrap_err(),
+            "missing `root` configuration"
+        );
+        assert_eq!(
+            registry.build("net", &context).unwrap_err(),
+            "no module named `net`"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! # Type ids.
+//!
+//! Rhai uses [`std::any::TypeId`] to recognize the type of values, but the type ids of the same type
+//! can differ between a plugin and its host depending on how they were built. Values created on one
+//! side are then not recognized by the other.
+//!
+//! Each plugin exports a [`TypeIds`] record, compared with the host's record before
+//! the entrypoint of the plugin is called.
+
+use super::compatibility::fingerprint;
+
+/// Fingerprints of the type ids of the core Rhai types.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeIds {
+    dynamic: u64,
+    immutable_string: u64,
+    int: u64,
+    array: u64,
+    blob: u64,
+    map: u64,
+    fn_ptr: u64,
+    module: u64,
+}
+
+impl TypeIds {
+    /// Get the type ids of the current build.
+    ///
+    /// Called from a plugin, this describes the plugin. Called from the host, this describes the host.
+    #[must_use]
+    pub fn current() -> Self {
+        Self {
+            dynamic: fingerprint::<rhai::Dynamic>(),
+            immutable_string: fingerprint::<rhai::ImmutableString>(),
+            int: fingerprint::<rhai::INT>(),
+            array: fingerprint::<rhai::Array>(),
+            blob: fingerprint::<rhai::Blob>(),
+            map: fingerprint::<rhai::Map>(),
+            fn_ptr: fingerprint::<rhai::FnPtr>(),
+            module: fingerprint::<rhai::Module>(),
+        }
+    }
+
+    /// List the names of the types whose ids differ between `self` and `other`.
+    #[must_use]
+    pub fn mismatches(&self, other: &Self) -> Vec<&'static str> {
+        self.entries()
+            .into_iter()
+            .zip(other.entries())
+            .filter(|((_, left), (_, right))| left != right)
+            .map(|((name, _), _)| name)
+            .collect()
+    }
+
+    /// Type names with their fingerprint.
+    const fn entries(&self) -> [(&'static str, u64); 8] {
+        [
+            ("Dynamic", self.dynamic),
+            ("ImmutableString", self.immutable_string),
+            ("INT", self.int),
+            ("Array", self.array),
+            ("Blob", self.blob),
+            ("Map", self.map),
+            ("FnPtr", self.fn_ptr),
+            ("Module", self.module),
+        ]
+    }
+}
+
+/// Error returned when the type ids of core Rhai types differ between a plugin and its host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeIdMismatch {
+    /// Names of the types whose ids differ.
+    pub types: Vec<&'static str>,
+}
+
+impl std::fmt::Display for TypeIdMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "type ids of the following types differ between the host and the plugin: {}",
+            self.types.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for TypeIdMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_matches_itself() {
+        assert!(TypeIds::current()
+            .mismatches(&TypeIds::current())
+            .is_empty());
+    }
+
+    #[test]
+    fn mismatching_types_are_listed() {
+        let host = TypeIds::current();
+        let plugin = TypeIds {
+            map: host.map.wrapping_add(1),
+            array: host.array.wrapping_add(1),
+            ..host
+        };
+
+        assert_eq!(host.mismatches(&plugin), vec!["Array", "Map"]);
+    }
+}
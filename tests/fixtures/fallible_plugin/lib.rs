@@ -0,0 +1,49 @@
+use rhai_dylib::plugin::context::HostContext;
+use rhai_dylib::rhai::{Engine, ImmutableString, Module, Shared};
+
+// A plugin failing to initialize without its configuration, used as a real dynamic library in unit tests.
+fn build_module(context: &HostContext) -> Result<Module, String> {
+    let greeting = context
+        .config()
+        .get("greeting")
+        .ok_or("missing `greeting` configuration")?
+        .clone()
+        .into_immutable_string()
+        .map_err(|actual| format!("`greeting` must be a string, not {actual}"))?;
+
+    Ok(greeter(greeting))
+}
+
+fn greeter(greeting: ImmutableString) -> Module {
+    let mut module = Module::new();
+
+    module.set_native_fn("greet", move |name: ImmutableString| {
+        Ok(format!("{greeting} {name}"))
+    });
+
+    module
+}
+
+// Registers a function, then fails if configured to.
+fn register(engine: &mut Engine, context: &HostContext) {
+    engine.register_fn("registered", || "registered".to_string());
+
+    assert!(
+        !context.config().contains_key("fail_engine"),
+        "configured to fail"
+    );
+}
+
+// An entrypoint under another name, with a default greeting, looked up by unit tests.
+#[allow(improper_ctypes_definitions)]
+#[no_mangle]
+pub extern "C" fn greeter_entrypoint() -> Shared<Module> {
+    greeter("Hi".into()).into()
+}
+
+rhai_dylib::declare_plugin!(
+    entrypoint_with_context = build_module,
+    engine = register,
+    name = "fallible_plugin",
+    description = "A plugin failing to initialize, used by unit tests",
+);
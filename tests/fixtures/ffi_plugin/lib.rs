@@ -0,0 +1,63 @@
+use rhai_dylib::plugin::ffi::{FfiCall, FfiData, FfiHost, FfiModule, FfiStr, FfiValue};
+use rhai_dylib::plugin::metadata::Metadata;
+use std::ffi::c_void;
+
+// A plugin using the C ABI only, as a plugin written in another language would, used in unit tests.
+
+extern "C" fn multiply(
+    _: *mut c_void,
+    args: *const FfiValue,
+    num_args: usize,
+    call: *mut FfiCall,
+    host: &FfiHost,
+) {
+    let args = unsafe { std::slice::from_raw_parts(args, num_args) };
+
+    match args.iter().map(FfiValue::read).collect::<Vec<_>>()[..] {
+        [Ok(FfiData::Int(a)), Ok(FfiData::Int(b))] => {
+            (host.return_value)(call, FfiData::Int(a * b).into());
+        }
+        _ => (host.return_error)(call, FfiStr::new("expected two integers")),
+    }
+}
+
+extern "C" fn shout(
+    _: *mut c_void,
+    args: *const FfiValue,
+    num_args: usize,
+    call: *mut FfiCall,
+    host: &FfiHost,
+) {
+    let args = unsafe { std::slice::from_raw_parts(args, num_args) };
+
+    match args.iter().map(FfiValue::read).collect::<Vec<_>>()[..] {
+        [Ok(FfiData::String(text))] => {
+            let text = unsafe { text.as_str() }.unwrap_or_default().to_uppercase();
+            (host.return_value)(call, FfiData::String(FfiStr::new(&text)).into());
+        }
+        _ => (host.return_error)(call, FfiStr::new("expected a string")),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn module_ffi_entrypoint(host: &FfiHost, module: *mut FfiModule) {
+    (host.register_fn)(
+        module,
+        FfiStr::new("multiply"),
+        2,
+        multiply,
+        std::ptr::null_mut(),
+    );
+    (host.register_fn)(module, FfiStr::new("shout"), 1, shout, std::ptr::null_mut());
+}
+
+#[no_mangle]
+pub extern "C" fn module_metadata() -> Metadata {
+    Metadata::builder(
+        "ffi_plugin",
+        "1.0.0",
+        "A plugin using the C ABI, used by unit tests",
+        "",
+    )
+    .build()
+}
@@ -0,0 +1,11 @@
+use rhai_dylib::rhai::{Module, Shared, INT};
+
+// A plugin only exporting its entrypoint, like plugins built before the metadata, compatibility,
+// type ids and hashing records, used as a real dynamic library in unit tests.
+#[allow(improper_ctypes_definitions)]
+#[no_mangle]
+pub extern "C" fn module_entrypoint() -> Shared<Module> {
+    let mut module = Module::new();
+    module.set_native_fn("answer", || Ok(42 as INT));
+    module.into()
+}
@@ -0,0 +1,62 @@
+use rhai_dylib::plugin::compatibility::Compatibility;
+use rhai_dylib::plugin::hashing::Hashing;
+use rhai_dylib::plugin::metadata::Metadata;
+use rhai_dylib::plugin::type_ids::TypeIds;
+use rhai_dylib::rhai::{Engine, Module, Shared, INT};
+
+// A plugin hashing functions with its own seed, used as a real dynamic library in unit tests.
+//
+// The tests build it with another `-C metadata`, as a third party would in its own workspace:
+// its layouts match the host's but the type ids of rhai types do not.
+
+pub struct Answer;
+
+#[no_mangle]
+pub extern "C" fn module_type_ids() -> TypeIds {
+    TypeIds::current()
+}
+
+#[no_mangle]
+pub extern "C" fn module_compatibility() -> *const Compatibility {
+    Compatibility::record()
+}
+
+#[no_mangle]
+pub extern "C" fn module_hashing() -> Hashing {
+    Hashing::current()
+}
+
+#[no_mangle]
+pub extern "C" fn module_metadata() -> Metadata {
+    Metadata::builder(
+        "mismatched_plugin",
+        "1.0.0",
+        "A plugin with mismatching type ids, used by unit tests",
+        "",
+    )
+    .build()
+}
+
+#[allow(improper_ctypes_definitions)]
+#[no_mangle]
+pub extern "C" fn module_entrypoint() -> Shared<Module> {
+    let _ = rhai_dylib::rhai::config::hashing::set_hashing_seed(Some([5, 6, 7, 8]));
+
+    let mut module = Module::new();
+    module.set_native_fn("answer", || Ok(42 as INT));
+
+    // Items lost or carried over when the host hashes the functions of the module again.
+    module.set_iter(std::any::TypeId::of::<Answer>(), |_| {
+        Box::new(std::iter::empty())
+    });
+    module.set_custom_type::<Answer>("Answer");
+
+    let ast = Engine::new_raw()
+        .compile("private fn hidden() { 42 }")
+        .expect("failed to compile the private function");
+    for function in ast.iter_fn_def() {
+        module.set_script_fn(function.clone());
+    }
+
+    module.into()
+}
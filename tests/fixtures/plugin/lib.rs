@@ -1,12 +1,40 @@
-use rhai_dylib::rhai::{config::hashing::set_hashing_seed, Module, Shared, INT};
+use rhai_dylib::plugin::context::HostContext;
+use rhai_dylib::rhai::{Engine, Module, INT};
 
 // A really simple plugin used as a real dynamic library in unit tests.
-#[allow(improper_ctypes_definitions)]
-#[no_mangle]
-pub extern "C" fn module_entrypoint() -> Shared<Module> {
-    let _ = set_hashing_seed(Some([1, 2, 3, 4]));
+fn build_module(context: &HostContext) -> Module {
+    assert!(
+        !context.config().contains_key("panic"),
+        "configured to panic"
+    );
+
     let mut module = Module::new();
 
     module.set_native_fn("add", |a: INT, b: INT| Ok(a + b));
-    module.into()
+    module.set_native_fn("plus", |a: INT, b: INT| Ok(a + b));
+    module.set_native_fn("fail", || -> Result<INT, _> { panic!("function panicked") });
+
+    let host = format!("{} {}", context.host_name(), context.host_version());
+    module.set_native_fn("host", move || Ok(host.clone()));
+
+    let answer = context.services().get::<INT>("answer").unwrap_or_default();
+    module.set_native_fn("answer", move || Ok(answer));
+
+    let config = context.config().clone();
+    module.set_native_fn("config", move || Ok(config.clone()));
+
+    module
+}
+
+fn register(engine: &mut Engine, _: &HostContext) {
+    engine
+        .register_custom_operator("plus", 160)
+        .expect("failed to register `plus`");
 }
+
+rhai_dylib::declare_plugin!(
+    entrypoint_with_context = build_module,
+    engine = register,
+    name = "test_plugin",
+    description = "A plugin used by unit tests",
+);
@@ -0,0 +1,44 @@
+use rhai_dylib::plugin::context::HostContext;
+use rhai_dylib::plugin::registry::ModuleRegistry;
+use rhai_dylib::rhai::{ImmutableString, Module, INT};
+
+// A plugin exporting several modules, used as a real dynamic library in unit tests.
+
+fn build_math_module(_: &HostContext) -> Module {
+    let mut module = Module::new();
+    module.set_native_fn("square", |value: INT| Ok(value * value));
+    module
+}
+
+fn build_constants_module(_: &HostContext) -> Module {
+    let mut module = Module::new();
+    module.set_var("answer", 42 as INT);
+    module
+}
+
+fn build_text_module(context: &HostContext) -> Result<Module, String> {
+    if context.config().contains_key("no_text") {
+        return Err("text module disabled".to_string());
+    }
+
+    let mut module = Module::new();
+    module.set_native_fn("shout", |text: ImmutableString| Ok(text.to_uppercase()));
+    Ok(module)
+}
+
+fn build_registry() -> ModuleRegistry {
+    let mut registry = ModuleRegistry::new();
+
+    registry
+        .register("constants", build_constants_module)
+        .register("math", build_math_module)
+        .register("text", build_text_module);
+
+    registry
+}
+
+rhai_dylib::declare_plugin!(
+    registry = build_registry,
+    name = "registry_plugin",
+    description = "A plugin exporting several modules, used by unit tests",
+);